@@ -0,0 +1,268 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::*;
+use tauri::{AppHandle, Manager};
+
+use crate::{aws_iprange::AwsIpRanges, settings_manager};
+
+const SETTINGS_FILE: &str = "settings.json";
+const IP_RANGES_FILE: &str = "ip-ranges.json";
+/// Left in `config_dir` once the legacy files have been looked at.
+const MIGRATION_MARKER_FILE: &str = ".legacy-migrated";
+
+/// Per-user directories of the app, created on [`AppPaths::resolve`].
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    pub config_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub log_dir: PathBuf
+}
+
+impl AppPaths {
+    /// `data_dir` replaces the platform directories, e.g. from `--data-dir`, see [`AppPaths::under`].
+    pub fn resolve(app_handle: &AppHandle, data_dir: Option<PathBuf>) -> Result<Self> {
+        let app_paths = match data_dir {
+            Some(data_dir) => Self::under(data_dir),
+            None => {
+                let path = app_handle.path();
+
+                Self {
+                    config_dir: path.app_config_dir()?,
+                    cache_dir: path.app_cache_dir()?,
+                    data_dir: path.app_data_dir()?,
+                    log_dir: path.app_log_dir()?
+                }
+            },
+        };
+        app_paths.create_dirs()?;
+
+        Ok(app_paths)
+    }
+
+    /// Settings file [`AppPaths::resolve`] ends up with, known before the app is built so the log plugin
+    /// can be configured from it. Without `data_dir` this mirrors the path resolver's app config dir.
+    pub fn settings_file_before_start(data_dir: Option<&Path>, identifier: &str) -> Option<PathBuf> {
+        match data_dir {
+            Some(data_dir) => Some(Self::under(data_dir.to_path_buf()).settings_file()),
+            None => dirs::config_dir().map(|config_dir| config_dir.join(identifier).join(SETTINGS_FILE)),
+        }
+    }
+
+    /// Keeps everything below `root`, e.g. for a portable install or a second profile.
+    pub fn under(root: PathBuf) -> Self {
+        Self {
+            config_dir: root.join("settings"),
+            cache_dir: root.join("cache"),
+            log_dir: root.join("logs"),
+            data_dir: root
+        }
+    }
+
+    fn create_dirs(&self) -> Result<()> {
+        for dir in [&self.config_dir, &self.cache_dir, &self.data_dir, &self.log_dir] {
+            fs::create_dir_all(dir).with_context(|| format!("could not create {:?}", dir))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn settings_file(&self) -> PathBuf {
+        self.config_dir.join(SETTINGS_FILE)
+    }
+
+    pub fn ip_ranges_file(&self) -> PathBuf {
+        self.cache_dir.join(IP_RANGES_FILE)
+    }
+
+    /// Moves files older builds wrote to one of `legacy_dirs`, the first directory holding a file wins,
+    /// files already present in the new location win over all of them and files that don't parse stay
+    /// where they are. The marker left in `config_dir` once every directory has been checked makes
+    /// later calls return right away, a failed directory is retried on the next start.
+    pub fn migrate_legacy_files(&self, legacy_dirs: &[PathBuf]) -> Result<()> {
+        let marker = self.config_dir.join(MIGRATION_MARKER_FILE);
+
+        if marker.exists() {
+            return Ok(());
+        }
+
+        let mut failure = None;
+
+        for legacy_dir in legacy_dirs {
+            if let Err(err) = self.migrate_legacy_dir(legacy_dir) {
+                error!("Could not migrate files from {:?}: {:?}", legacy_dir, err);
+                failure = Some(err);
+            }
+        }
+
+        if let Some(err) = failure {
+            return Err(err);
+        }
+
+        fs::write(&marker, Utc::now().to_rfc3339()).with_context(|| format!("could not write {:?}", marker))?;
+
+        Ok(())
+    }
+
+    fn migrate_legacy_dir(&self, legacy_dir: &Path) -> Result<()> {
+        for (file_name, destination) in [
+            (SETTINGS_FILE, self.settings_file()),
+            (IP_RANGES_FILE, self.ip_ranges_file())
+        ] {
+            let source = legacy_dir.join(file_name);
+
+            if !source.is_file() || destination.exists() {
+                continue;
+            }
+
+            let bytes = fs::read(&source).with_context(|| format!("could not read {:?}", source))?;
+
+            if let Err(err) = check_legacy_file(file_name, &bytes) {
+                warn!("Not moving {:?}, it is not a file of this app: {:#}", source, err);
+                continue;
+            }
+
+            info!("Moving {:?} to {:?}", source, destination);
+
+            // rename fails across file systems, fall back to copying
+            if fs::rename(&source, &destination).is_err() {
+                fs::write(&destination, &bytes)
+                    .with_context(|| format!("could not copy {:?} to {:?}", source, destination))?;
+
+                if let Err(err) = fs::remove_file(&source) {
+                    warn!("Could not remove {:?}: {}", source, err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Directories older builds may have written to. They used relative paths, which resolve against the
+/// working directory the app was launched from, usually but not always the install directory.
+pub fn legacy_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+
+    if let Ok(current_dir) = std::env::current_dir() {
+        dirs.push(current_dir);
+    }
+
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+    if let Some(exe_dir) = exe_dir {
+        if !dirs.contains(&exe_dir) {
+            dirs.push(exe_dir);
+        }
+    }
+
+    dirs
+}
+
+/// Checks that a legacy file was written by this app before it is moved.
+fn check_legacy_file(file_name: &str, bytes: &[u8]) -> Result<()> {
+    match file_name {
+        SETTINGS_FILE => settings_manager::parse(bytes).map(|_| ()),
+        _ => serde_json::from_slice::<AwsIpRanges>(bytes).map(|_| ()).map_err(Into::into),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn temp_paths() -> (PathBuf, AppPaths) {
+        let root = std::env::temp_dir().join(format!("lost-metrics-{}", Uuid::now_v7()));
+        let app_paths = AppPaths::under(root.clone());
+        app_paths.create_dirs().unwrap();
+
+        (root, app_paths)
+    }
+
+    #[test]
+    fn should_keep_every_file_under_data_dir() {
+        let root = PathBuf::from("portable");
+        let app_paths = AppPaths::under(root.clone());
+
+        assert_eq!(app_paths.data_dir, root);
+        assert_eq!(app_paths.settings_file(), root.join("settings").join(SETTINGS_FILE));
+        assert_eq!(app_paths.ip_ranges_file(), root.join("cache").join(IP_RANGES_FILE));
+        assert_eq!(app_paths.log_dir, root.join("logs"));
+        assert_eq!(AppPaths::settings_file_before_start(Some(&root), "app.identifier"), Some(app_paths.settings_file()));
+    }
+
+    #[test]
+    fn should_move_legacy_files_once() {
+        let (root, app_paths) = temp_paths();
+        let legacy_dir = root.join("legacy");
+        let legacy_settings = include_str!("../default_settings.json");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join(SETTINGS_FILE), legacy_settings).unwrap();
+        fs::write(legacy_dir.join(IP_RANGES_FILE), "legacy").unwrap();
+        fs::write(app_paths.ip_ranges_file(), "current").unwrap();
+
+        app_paths.migrate_legacy_files(&[legacy_dir.clone()]).unwrap();
+
+        assert_eq!(fs::read_to_string(app_paths.settings_file()).unwrap(), legacy_settings);
+        assert_eq!(fs::read_to_string(app_paths.ip_ranges_file()).unwrap(), "current");
+        assert!(!legacy_dir.join(SETTINGS_FILE).exists());
+        assert!(legacy_dir.join(IP_RANGES_FILE).exists());
+
+        fs::remove_file(app_paths.settings_file()).unwrap();
+        fs::write(legacy_dir.join(SETTINGS_FILE), legacy_settings).unwrap();
+
+        app_paths.migrate_legacy_files(&[legacy_dir.clone()]).unwrap();
+
+        assert!(!app_paths.settings_file().exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn should_leave_foreign_files_in_place() {
+        let (root, app_paths) = temp_paths();
+        let legacy_dir = root.join("legacy");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join(SETTINGS_FILE), r#"{ "theme": "dark" }"#).unwrap();
+
+        app_paths.migrate_legacy_files(&[legacy_dir.clone()]).unwrap();
+
+        assert!(!app_paths.settings_file().exists());
+        assert!(legacy_dir.join(SETTINGS_FILE).exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn should_check_every_legacy_dir_before_leaving_marker() {
+        let (root, app_paths) = temp_paths();
+        let working_dir = root.join("working");
+        let exe_dir = root.join("install");
+        let ip_ranges = r#"{ "syncToken": "1", "createDate": "2025-01-01-00-00-00", "prefixes": [] }"#;
+        fs::create_dir_all(&working_dir).unwrap();
+        fs::create_dir_all(&exe_dir).unwrap();
+        fs::write(working_dir.join(SETTINGS_FILE), include_str!("../default_settings.json")).unwrap();
+        fs::write(exe_dir.join(IP_RANGES_FILE), ip_ranges).unwrap();
+        fs::remove_dir_all(&app_paths.cache_dir).unwrap();
+
+        let legacy_dirs = [working_dir.clone(), exe_dir.clone()];
+
+        assert!(app_paths.migrate_legacy_files(&legacy_dirs).is_err());
+        assert!(app_paths.settings_file().exists());
+        assert!(!app_paths.config_dir.join(MIGRATION_MARKER_FILE).exists());
+
+        fs::create_dir_all(&app_paths.cache_dir).unwrap();
+        app_paths.migrate_legacy_files(&legacy_dirs).unwrap();
+
+        assert_eq!(fs::read_to_string(app_paths.ip_ranges_file()).unwrap(), ip_ranges);
+        assert!(app_paths.config_dir.join(MIGRATION_MARKER_FILE).exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
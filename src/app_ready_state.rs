@@ -1,4 +1,4 @@
-use std::sync::{Arc, Condvar, Mutex};
+use std::{sync::{Arc, Condvar, Mutex}, time::Duration};
 
 pub struct AppReadyState {
     ready: Mutex<bool>,
@@ -25,4 +25,13 @@ impl AppReadyState {
             is_ready = self.condvar.wait(is_ready).unwrap();
         }
     }
+
+    /// Waits up to `timeout` for the app to become ready, returns whether it is.
+    pub fn wait_for_ready_timeout(&self, timeout: Duration) -> bool {
+        let is_ready = self.ready.lock().unwrap();
+        let (is_ready, _) = self.condvar
+            .wait_timeout_while(is_ready, timeout, |is_ready| !*is_ready)
+            .unwrap();
+        *is_ready
+    }
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use anyhow::Result;
+use log::*;
+use tauri::AppHandle;
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::models::AutostartSettings;
+
+/// Argument the OS entry starts the app with, see [`launched_at_login`].
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+pub fn launched_at_login() -> bool {
+    std::env::args().any(|arg| arg == AUTOSTART_ARG)
+}
+
+/// Arguments of the OS entry, a data directory override is passed on so the app started at login
+/// reads the same settings. It is made absolute as the entry does not start in the current directory.
+pub fn autostart_args(data_dir: Option<&Path>) -> Vec<String> {
+    let mut args = vec![AUTOSTART_ARG.to_string()];
+
+    if let Some(data_dir) = data_dir {
+        let data_dir = std::path::absolute(data_dir).unwrap_or_else(|_| data_dir.to_path_buf());
+        args.push("--data-dir".to_string());
+        args.push(data_dir.to_string_lossy().into_owned());
+    }
+
+    args
+}
+
+/// Registers or removes the OS entry, a registry run key on Windows, a launch agent on macOS
+/// and an XDG autostart desktop entry on Linux.
+pub fn apply_autostart(app_handle: &AppHandle, enabled: bool) -> Result<()> {
+    let autolaunch = app_handle.autolaunch();
+
+    if autolaunch.is_enabled()? == enabled {
+        return Ok(());
+    }
+
+    info!("{} launch at login", if enabled { "Enabling" } else { "Disabling" });
+
+    if enabled {
+        autolaunch.enable()?;
+    } else {
+        autolaunch.disable()?;
+    }
+
+    Ok(())
+}
+
+/// Outcome of comparing the launch at login setting to the OS entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutostartReconciliation {
+    InSync,
+    /// The entry was removed outside of the app, e.g. from the startup apps of the OS,
+    /// the setting is turned off rather than the entry being registered again.
+    TurnSettingOff,
+    /// The setting is off but an entry is left over, it is removed.
+    RemoveEntry,
+    /// The entry may predate the data directory override, it is registered again
+    /// so it starts the app with the same one.
+    RegisterAgain
+}
+
+pub fn reconcile(enabled: bool, is_registered: bool, data_dir_overridden: bool) -> AutostartReconciliation {
+    match (enabled, is_registered) {
+        (true, false) => AutostartReconciliation::TurnSettingOff,
+        (false, true) => AutostartReconciliation::RemoveEntry,
+        (true, true) if data_dir_overridden => AutostartReconciliation::RegisterAgain,
+        _ => AutostartReconciliation::InSync,
+    }
+}
+
+/// Compares the settings to the OS entry on startup and returns whether launch at login stays enabled.
+pub fn reconcile_autostart(app_handle: &AppHandle, autostart: &AutostartSettings, data_dir_overridden: bool) -> Result<bool> {
+    let autolaunch = app_handle.autolaunch();
+    let is_registered = autolaunch.is_enabled()?;
+
+    match reconcile(autostart.enabled, is_registered, data_dir_overridden) {
+        AutostartReconciliation::InSync => {},
+        AutostartReconciliation::TurnSettingOff => {
+            warn!("Launch at login was removed outside of the app, turning it off");
+            return Ok(false);
+        },
+        AutostartReconciliation::RemoveEntry => apply_autostart(app_handle, false)?,
+        AutostartReconciliation::RegisterAgain => {
+            debug!("Registering launch at login again with the data directory override");
+            autolaunch.disable()?;
+            autolaunch.enable()?;
+        },
+    }
+
+    Ok(autostart.enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_matching_setting_and_entry() {
+        assert_eq!(reconcile(true, true, false), AutostartReconciliation::InSync);
+        assert_eq!(reconcile(false, false, false), AutostartReconciliation::InSync);
+        assert_eq!(reconcile(false, false, true), AutostartReconciliation::InSync);
+    }
+
+    #[test]
+    fn should_turn_setting_off_when_entry_was_removed() {
+        assert_eq!(reconcile(true, false, false), AutostartReconciliation::TurnSettingOff);
+        assert_eq!(reconcile(true, false, true), AutostartReconciliation::TurnSettingOff);
+    }
+
+    #[test]
+    fn should_remove_entry_left_over_when_setting_is_off() {
+        assert_eq!(reconcile(false, true, false), AutostartReconciliation::RemoveEntry);
+        assert_eq!(reconcile(false, true, true), AutostartReconciliation::RemoveEntry);
+    }
+
+    #[test]
+    fn should_register_again_with_data_dir_override() {
+        assert_eq!(reconcile(true, true, true), AutostartReconciliation::RegisterAgain);
+    }
+
+    #[test]
+    fn should_forward_data_dir_to_autostart_entry() {
+        let data_dir = std::env::temp_dir().join("lost-metrics-portable");
+
+        assert_eq!(autostart_args(None), vec![AUTOSTART_ARG]);
+        assert_eq!(autostart_args(Some(&data_dir)), vec![
+            AUTOSTART_ARG.to_string(),
+            "--data-dir".to_string(),
+            data_dir.to_string_lossy().into_owned()
+        ]);
+    }
+}
@@ -31,9 +31,9 @@ pub struct AwsIpRange {
 
 impl AwsIpRange {
 
-    pub fn new() -> Self {
+    /// `cached_json` is usually [`AppPaths::ip_ranges_file`](crate::app_paths::AppPaths::ip_ranges_file).
+    pub fn new(cached_json: PathBuf) -> Self {
         let url = "https://ip-ranges.amazonaws.com/ip-ranges.json".into();
-        let cached_json = "ip-ranges.json".into();
         let client = Client::new();
 
         Self { url, cached_json, client }
@@ -1,98 +1,155 @@
-use crate::{app_ready_state::AppReadyState, models::{ProcessState, ProcessWatcherResult, SnifferSettings}, process_watcher::ProcessWatcher, processor::Processor, updater::AppUpdater};
+use crate::{app_ready_state::AppReadyState, instance_registry::InstanceRegistry, models::{ConnectionQualityResult, ProcessState, ProcessWatcherResult, ReconnectInfo, SnifferSettings, WatcherEvent}, process_watcher::{ProcessMatcher, ProcessWatcher}, processor::Processor, stop_signal::StopSignal, updater::AppUpdater};
 use std::{
-    error::Error, sync::Arc, thread::JoinHandle, time::Duration
+    collections::HashMap, error::Error, sync::{mpsc::RecvTimeoutError, Arc}, thread::JoinHandle, time::Duration
 };
 use chrono::Utc;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use lost_metrics_simulator::simulator::Simulator;
 use tauri::{App, AppHandle, Emitter, Listener, Manager};
 use tokio::{runtime::{Handle, Runtime}, sync::Mutex, task};
 use anyhow::Result;
+use uuid::Uuid;
 
 pub struct BackgroundWorker {
     app_handle: AppHandle,
     app_updater: Arc<Mutex<AppUpdater>>,
     process_watcher: Arc<Mutex<ProcessWatcher>>,
     app_ready_state: Arc<AppReadyState>,
+    instance_registry: Arc<Mutex<InstanceRegistry>>,
     sniffer_settings: SnifferSettings,
+    stop_signal: Arc<StopSignal>,
     handle: Option<JoinHandle<anyhow::Result<()>>>
 }
 
+/// How often a worker still waiting for the app checks whether it has been stopped.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 impl BackgroundWorker {
     pub fn new(
         app_handle: AppHandle,
         app_updater: Arc<Mutex<AppUpdater>>,
         process_watcher: Arc<Mutex<ProcessWatcher>>,
         app_ready_state: Arc<AppReadyState>,
+        instance_registry: Arc<Mutex<InstanceRegistry>>,
         sniffer_settings: SnifferSettings) -> Self {
         Self {
             app_handle,
             app_updater,
             process_watcher,
             app_ready_state,
+            instance_registry,
             sniffer_settings,
+            stop_signal: Arc::new(StopSignal::new()),
             handle: None
         }
     }
 
     pub fn start(&mut self) {
         let process_watcher = self.process_watcher.clone();
-        let mut processor = Processor::new(self.app_handle.clone());
+        let instance_registry = self.instance_registry.clone();
         let app_handle = self.app_handle.clone();
         let app_updater = self.app_updater.clone();
         let app_ready_state = self.app_ready_state.clone();
         let sniffer_settings = self.sniffer_settings.clone();
+        self.stop_signal = Arc::new(StopSignal::new());
+        let stop_signal = self.stop_signal.clone();
 
         let handle = std::thread::spawn(move || {
             let rt = Runtime::new().expect("Failed to create runtime");
 
             rt.block_on(async {
                 info!("waiting for load");
-                app_ready_state.wait_for_ready();
+                while !app_ready_state.wait_for_ready_timeout(READY_POLL_INTERVAL) {
+                    if stop_signal.is_stopped() {
+                        return anyhow::Ok(());
+                    }
+                }
                 // setup_update_checker(app_handle.clone(), app_updater).await?;
         
+                let matcher = ProcessMatcher::new(&sniffer_settings)?;
                 let rx = {
                     let mut process_watcher = process_watcher.lock().await;
-                    process_watcher.start(&sniffer_settings.process_name, sniffer_settings.port)
+
+                    // stopped while waiting, checked under the lock so stop() can't miss the watcher
+                    if stop_signal.is_stopped() {
+                        return anyhow::Ok(());
+                    }
+
+                    process_watcher.set_check_interval(sniffer_settings.check_interval);
+                    process_watcher.start(matcher, sniffer_settings.port)
                 };
         
-                let mut process_state = ProcessState::Unknown;
+                let mut processors: HashMap<Uuid, Processor> = HashMap::new();
                 let recv_timeout = Duration::from_secs(2);
     
                 loop {
-                    let message = match rx.recv_timeout(recv_timeout) {
-                        Ok(message) => {
-                            process_state = message.clone();
-                            message
+                    let event = match rx.recv_timeout(recv_timeout) {
+                        Ok(WatcherEvent::State(event)) => event,
+                        Ok(WatcherEvent::ConnectionSample { instance_id, sample }) => {
+                            if let Some(processor) = processors.get(&instance_id) {
+                                let result = ConnectionQualityResult {
+                                    instance_id,
+                                    quality: processor.record_sample(&sample),
+                                    sample
+                                };
+
+                                app_handle.emit("connection-quality", result)?;
+                            }
+
+                            continue;
                         },
-                        Err(_) => {
-                            let process_watcher = process_watcher.lock().await;
-                            
-                            if !process_watcher.is_running() {
-                                break;
+                        // the watcher thread exited, either stopped or failed
+                        Err(RecvTimeoutError::Disconnected) => break,
+                        Err(RecvTimeoutError::Timeout) => {
+                            // nothing changed, repeat the latest states so the UI knows the watcher is alive
+                            let results = instance_registry.lock().await.current();
+
+                            for mut result in results {
+                                result.checked_on = Utc::now();
+                                app_handle.emit("process-check", result)?;
                             }
         
-                            ProcessState::Unknown
+                            continue;
                         },
                     };
-    
+
                     let result = ProcessWatcherResult {
+                        instance_id: event.instance_id,
                         checked_on: Utc::now(),
-                        state: process_state.clone()
+                        state: event.state.clone()
                     };
-    
+
+                    instance_registry.lock().await.update(&result);
                     app_handle.emit("process-check", result)?;
+
+                    let Some(instance_id) = event.instance_id else {
+                        continue;
+                    };
         
-                    match message {
-                        ProcessState::ProcessListening(region) => {
-                            processor.start(region);
+                    match event.state {
+                        ProcessState::ProcessListening(connection)
+                        | ProcessState::ProcessReconnected(ReconnectInfo { connection, .. }) => {
+                            // close the encounter of the previous connection before starting a new session
+                            if let Some(mut processor) = processors.remove(&instance_id) {
+                                processor.stop().await?;
+                            }
+
+                            let mut processor = Processor::new(app_handle.clone(), instance_id);
+                            processor.start(connection.region);
+                            processors.insert(instance_id, processor);
                         },
-                        ProcessState::ProcesStopped => {
-                            processor.stop();
+                        ProcessState::ProcessStopped(_) => {
+                            if let Some(mut processor) = processors.remove(&instance_id) {
+                                processor.stop().await?;
+                            }
                         },
                         _ => {}
                     }
                 }
+
+                for (_, mut processor) in processors.drain() {
+                    processor.stop().await?;
+                }
         
                 anyhow::Ok(()) 
             })
@@ -101,12 +158,39 @@ impl BackgroundWorker {
         self.handle = Some(handle);
     }
 
-    pub fn stop(&mut self) -> Result<()> {
+    /// Stops the process watcher and waits for the worker to close its encounters and exit.
+    ///
+    /// A watcher run that already failed is only logged, its error is kept in [`ProcessWatcher::last_error`].
+    pub async fn stop(&mut self) -> Result<()> {
+        self.stop_signal.stop();
+
+        if let Err(err) = self.process_watcher.lock().await.stop() {
+            warn!("Previous process watcher exited with an error: {:?}", err);
+        }
+
         if let Some(handle) = self.handle.take() {
-            handle.join()
-                .map_err(|err| anyhow::anyhow!("{:?}", err))?;
+            task::spawn_blocking(move || handle.join())
+                .await?
+                .map_err(|err| anyhow::anyhow!("{:?}", err))??;
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn sniffer_settings(&self) -> &SnifferSettings {
+        &self.sniffer_settings
+    }
+
+    /// Stops the worker and starts it again with the given sniffer settings.
+    ///
+    /// The worker is started even when the previous run failed, that error is returned for reporting.
+    pub async fn restart(&mut self, sniffer_settings: SnifferSettings) -> Result<()> {
+        let stopped = self.stop().await;
+        // the new watcher tracks clients under new instance ids
+        self.instance_registry.lock().await.reset();
+        self.sniffer_settings = sniffer_settings;
+        self.start();
+
+        stopped
+    }
+}
@@ -0,0 +1,86 @@
+use crate::models::{ClassInfo, ClassRole, DisplaySettings};
+
+/// Playable class as identified by the game client.
+pub struct ClassDefinition {
+    pub id: u32,
+    pub name: &'static str,
+    pub role: ClassRole,
+    pub color: &'static str
+}
+
+const fn class(id: u32, name: &'static str, role: ClassRole, color: &'static str) -> ClassDefinition {
+    ClassDefinition { id, name, role, color }
+}
+
+/// Advanced classes, base classes never show up in encounters.
+pub const CLASSES: [ClassDefinition; 28] = [
+    class(102, "Berserker", ClassRole::Dps, "#ee2e48"),
+    class(103, "Destroyer", ClassRole::Dps, "#7b9aa2"),
+    class(104, "Gunlancer", ClassRole::Dps, "#e1907e"),
+    class(105, "Paladin", ClassRole::Support, "#ff9900"),
+    class(112, "Slayer", ClassRole::Dps, "#db6a42"),
+    class(113, "Valkyrie", ClassRole::Support, "#f2d16b"),
+    class(202, "Arcanist", ClassRole::Dps, "#b38915"),
+    class(203, "Summoner", ClassRole::Dps, "#22aa99"),
+    class(204, "Bard", ClassRole::Support, "#674598"),
+    class(205, "Sorceress", ClassRole::Dps, "#66cc00"),
+    class(302, "Wardancer", ClassRole::Dps, "#aaaa11"),
+    class(303, "Scrapper", ClassRole::Dps, "#990099"),
+    class(304, "Soulfist", ClassRole::Dps, "#316395"),
+    class(305, "Glaivier", ClassRole::Dps, "#f6da6a"),
+    class(312, "Striker", ClassRole::Dps, "#994499"),
+    class(313, "Breaker", ClassRole::Dps, "#4de3d1"),
+    class(402, "Deathblade", ClassRole::Dps, "#a91a16"),
+    class(403, "Shadowhunter", ClassRole::Dps, "#0099c6"),
+    class(404, "Reaper", ClassRole::Dps, "#109618"),
+    class(405, "Souleater", ClassRole::Dps, "#c16ed0"),
+    class(502, "Sharpshooter", ClassRole::Dps, "#dd4477"),
+    class(503, "Deadeye", ClassRole::Dps, "#4442a8"),
+    class(504, "Artillerist", ClassRole::Dps, "#33670b"),
+    class(505, "Machinist", ClassRole::Dps, "#3b4292"),
+    class(512, "Gunslinger", ClassRole::Dps, "#6bcec2"),
+    class(602, "Artist", ClassRole::Support, "#a34af0"),
+    class(603, "Aeromancer", ClassRole::Dps, "#084ba3"),
+    class(604, "Wildsoul", ClassRole::Dps, "#3a9d5d"),
+];
+
+pub fn find_class(id: u32) -> Option<&'static ClassDefinition> {
+    CLASSES.iter().find(|class| class.id == id)
+}
+
+/// Every class with the colour overrides of `display` applied.
+pub fn resolve(display: &DisplaySettings) -> Vec<ClassInfo> {
+    CLASSES
+        .iter()
+        .map(|class| ClassInfo {
+            id: class.id,
+            name: class.name.to_string(),
+            role: class.role,
+            color: display.class_colors
+                .get(&class.id)
+                .cloned()
+                .unwrap_or_else(|| class.color.to_string()),
+            default_color: class.color.to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_apply_color_overrides() {
+        let mut display = DisplaySettings::default();
+        display.class_colors.insert(204, "#123456".into());
+
+        let classes = resolve(&display);
+        let bard = classes.iter().find(|class| class.id == 204).unwrap();
+        let berserker = classes.iter().find(|class| class.id == 102).unwrap();
+
+        assert_eq!(classes.len(), CLASSES.len());
+        assert_eq!(bard.color, "#123456");
+        assert_eq!(bard.default_color, "#674598");
+        assert_eq!(berserker.color, berserker.default_color);
+    }
+}
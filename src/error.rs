@@ -1,18 +1,44 @@
+use serde::ser::SerializeStruct;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Generic error")]
     Generic(#[from] Box<dyn std::error::Error>),
     #[error("Serialization")]
     Serde(#[from] serde_json::error::Error),
+    #[error("Invalid settings")]
+    Validation(Vec<crate::models::FieldError>),
+    #[error("Unknown profile {0}")]
+    UnknownProfile(String),
+    #[error("Profile {0} already exists")]
+    ProfileExists(String),
+    #[error("Profile {0} is active")]
+    ActiveProfile(String),
+    #[error("Unknown instance {0}")]
+    UnknownInstance(uuid::Uuid),
     #[error("Unknown error")]
     Unknown
 }
 
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        AppError::Generic(err.into())
+    }
+}
+
 impl serde::Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
+        // validation errors carry the offending fields so the UI can show them next to each input
+        if let AppError::Validation(field_errors) = self {
+            let mut state = serializer.serialize_struct("AppError", 2)?;
+            state.serialize_field("message", &self.to_string())?;
+            state.serialize_field("fieldErrors", field_errors)?;
+            return state.end();
+        }
+
         serializer.serialize_str(self.to_string().as_ref())
     }
 }
\ No newline at end of file
@@ -2,14 +2,14 @@ use chrono::Utc;
 use rand::{rng, Rng};
 use uuid::Uuid;
 
-use crate::models::{Boss, Encounter, Player};
+use crate::models::{Boss, ConnectionQuality, Encounter, Player};
 
 pub struct FakeEncounter {
     encounter: Encounter
 }
 
 impl FakeEncounter {
-    pub fn new() -> Self {
+    pub fn new(instance_id: Uuid) -> Self {
         let participants = vec![
             Player {
                 id: 1,
@@ -71,8 +71,10 @@ impl FakeEncounter {
 
         let encounter = Encounter {
             id: Uuid::now_v7(),
+            instance_id,
             updated_on: Utc::now(),
             total_damage: 0.into(),
+            connection_quality: None,
             participants,
             boss: Boss {
                 id: 1,
@@ -101,6 +103,10 @@ impl FakeEncounter {
 
     }
 
+    pub fn set_connection_quality(&mut self, quality: ConnectionQuality) {
+        self.encounter.connection_quality = Some(quality);
+    }
+
     pub fn get(&self) -> &Encounter {
         &self.encounter
     }
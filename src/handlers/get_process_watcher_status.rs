@@ -0,0 +1,31 @@
+
+use std::sync::Arc;
+use tauri::{command, State};
+use tokio::sync::Mutex;
+use crate::{error::AppError, instance_registry::InstanceRegistry, models::ProcessWatcherStatus, process_watcher::ProcessWatcher};
+
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+#[command]
+pub async fn get_process_watcher_status(
+    process_watcher: State<'_, Arc<Mutex<ProcessWatcher>>>,
+    instance_registry: State<'_, Arc<Mutex<InstanceRegistry>>>,
+    history_limit: Option<usize>) -> Result<ProcessWatcherStatus, AppError> {
+
+    let (is_alive, last_error) = {
+        let process_watcher = process_watcher.lock().await;
+        (process_watcher.is_thread_alive(), process_watcher.last_error())
+    };
+
+    let instance_registry = instance_registry.lock().await;
+
+    let status = ProcessWatcherStatus {
+        is_alive,
+        last_error,
+        active_instance_id: instance_registry.active_instance_id(),
+        current: instance_registry.current(),
+        history: instance_registry.history(history_limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+    };
+
+    Ok(status)
+}
@@ -0,0 +1,34 @@
+
+use std::sync::Arc;
+use tauri::{command, AppHandle, Emitter, State};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use crate::{error::AppError, instance_registry::InstanceRegistry, models::GetInstancesResult};
+
+#[command]
+pub async fn get_instances(
+    instance_registry: State<'_, Arc<Mutex<InstanceRegistry>>>) -> Result<GetInstancesResult, AppError> {
+
+    let instance_registry = instance_registry.lock().await;
+    
+    Ok(instance_registry.get())
+}
+
+#[command]
+pub async fn set_active_instance(
+    app_handle: AppHandle,
+    instance_registry: State<'_, Arc<Mutex<InstanceRegistry>>>,
+    instance_id: Uuid) -> Result<GetInstancesResult, AppError> {
+
+    let mut instance_registry = instance_registry.lock().await;
+
+    if !instance_registry.set_active(instance_id) {
+        return Err(AppError::UnknownInstance(instance_id));
+    }
+
+    let result = instance_registry.get();
+    app_handle.emit("active-instance-changed", instance_id)
+        .map_err(|err| AppError::Generic(Box::new(err)))?;
+    
+    Ok(result)
+}
@@ -0,0 +1,30 @@
+
+use std::sync::Arc;
+use tauri::{command, AppHandle, State};
+use tauri_plugin_shell::ShellExt;
+use crate::{app_paths::AppPaths, error::AppError, logging};
+
+const DEFAULT_LINE_COUNT: usize = 200;
+
+/// Most recent lines of the current log file, for attaching to bug reports.
+#[command]
+pub async fn get_recent_logs(
+    app_paths: State<'_, Arc<AppPaths>>,
+    line_count: Option<usize>) -> Result<Vec<String>, AppError> {
+
+    let lines = logging::read_recent_lines(&app_paths.log_dir, line_count.unwrap_or(DEFAULT_LINE_COUNT)).await?;
+
+    Ok(lines)
+}
+
+/// Opens the log directory in the file manager of the OS.
+#[command]
+pub async fn open_log_dir(
+    app_handle: AppHandle,
+    app_paths: State<'_, Arc<AppPaths>>) -> Result<(), AppError> {
+
+    app_handle.shell().open(app_paths.log_dir.to_string_lossy(), None)
+        .map_err(|err| AppError::Generic(Box::new(err)))?;
+
+    Ok(())
+}
@@ -7,15 +7,38 @@ mod run_simulation;
 mod get_simulation_templates;
 mod get_stats;
 mod get_past_encounters;
+mod instances;
+mod get_process_watcher_status;
+mod profiles;
+mod logs;
+mod shortcuts;
 
 pub fn generate_handlers() -> Box<dyn Fn(tauri::ipc::Invoke) -> bool + Send + Sync> {
     Box::new(generate_handler![
         load::load,
         settings::get_settings,
+        settings::get_class_registry,
         settings::save_settings,
+        settings::patch_settings,
+        settings::export_settings,
+        settings::preview_settings_import,
+        settings::import_settings,
+        settings::reset_window_layout,
+        profiles::get_profiles,
+        profiles::create_profile,
+        profiles::clone_profile,
+        profiles::rename_profile,
+        profiles::delete_profile,
+        profiles::activate_profile,
+        shortcuts::get_shortcut_status,
         run_simulation::run_simulation,
         get_simulation_templates::get_simulation_templates,
         get_stats::get_stats,
-        get_past_encounters::get_past_encounters
+        get_past_encounters::get_past_encounters,
+        instances::get_instances,
+        instances::set_active_instance,
+        get_process_watcher_status::get_process_watcher_status,
+        logs::get_recent_logs,
+        logs::open_log_dir
     ])
 }
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+use tauri::{command, State};
+use tokio::sync::Mutex;
+use crate::{error::AppError, models::ProfilesResult, settings_manager::SettingsManager};
+
+#[command]
+pub async fn get_profiles(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>) -> Result<ProfilesResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    
+    settings_manager.get_profiles().await
+}
+
+/// Adds a profile with the default settings.
+#[command]
+pub async fn create_profile(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    name: String) -> Result<ProfilesResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    
+    settings_manager.create_profile(&name).await
+}
+
+#[command]
+pub async fn clone_profile(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    source: String,
+    name: String) -> Result<ProfilesResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    
+    settings_manager.clone_profile(&source, &name).await
+}
+
+#[command]
+pub async fn rename_profile(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    name: String,
+    new_name: String) -> Result<ProfilesResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    
+    settings_manager.rename_profile(&name, &new_name).await
+}
+
+#[command]
+pub async fn delete_profile(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    name: String) -> Result<ProfilesResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    
+    settings_manager.delete_profile(&name).await
+}
+
+/// Switches the active profile, its settings are applied like saved settings.
+#[command]
+pub async fn activate_profile(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    name: String) -> Result<ProfilesResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    
+    settings_manager.activate_profile(&name).await
+}
@@ -4,25 +4,140 @@ use log::info;
 use tauri::{command, App, AppHandle, State};
 use tokio::sync::Mutex;
 use std::error::Error as StdError;
-use crate::{app_ready_state::AppReadyState, error::AppError, models::{LoadResult, Settings}, settings_manager::{self, SettingsManager}};
+use std::path::PathBuf;
+use serde_json::Value;
+use crate::{app_paths::AppPaths, app_ready_state::AppReadyState, class_registry, error::AppError, merge_patch::merge_patch, models::{ExportResult, GetClassRegistryResult, GetSettingsResult, ImportPreview, LoadResult, Settings}, settings_manager::{self, SettingsManager}, settings_transfer, window_layout};
 
+/// Effective settings of the active profile, `sources` tells which values come from the command line or environment.
 #[command]
 pub async fn get_settings(
-    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>) -> Result<Settings, AppError> {
+    app_paths: State<'_, Arc<AppPaths>>,
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>) -> Result<GetSettingsResult, AppError> {
 
     let mut settings_manager = settings_manager.lock().await;
     let settings = settings_manager.get_or_create_default().await?;
     
-    Ok(settings)
+    Ok(GetSettingsResult {
+        settings,
+        data_dir: app_paths.data_dir.clone(),
+        sources: settings_manager.overrides().sources()
+    })
 }
 
 
+/// Classes with their effective colours and the display settings of the active profile.
+#[command]
+pub async fn get_class_registry(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>) -> Result<GetClassRegistryResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    let settings = settings_manager.get_or_create_default().await?;
+
+    Ok(GetClassRegistryResult {
+        classes: class_registry::resolve(&settings.display),
+        display: settings.display
+    })
+}
+
+/// Saves the settings, subscribers such as the process watcher pick up the change.
 #[command]
 pub async fn save_settings(
-    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>, settings: Settings) -> Result<(), AppError> {
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    settings: Settings) -> Result<(), AppError> {
+
+    settings.validate().map_err(AppError::Validation)?;
 
     let mut settings_manager = settings_manager.lock().await;
     settings_manager.save(&settings).await?;
     
     Ok(())
 }
+
+/// Applies a JSON merge patch (RFC 7396) to the current settings and saves the result,
+/// so windows editing different sections don't overwrite each other.
+#[command]
+pub async fn patch_settings(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    patch: Value) -> Result<Settings, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    let settings = settings_manager.get_or_create_default().await?;
+
+    let mut value = serde_json::to_value(&settings)?;
+    merge_patch(&mut value, &patch);
+
+    let settings: Settings = serde_json::from_value(value)?;
+    settings.validate().map_err(AppError::Validation)?;
+    settings_manager.save(&settings).await?;
+
+    Ok(settings)
+}
+
+/// Writes the active profile as stored to `path`, startup overrides are not exported and
+/// [`Settings::SECRET_FIELDS`] are left out unless `include_secrets` is set.
+#[command]
+pub async fn export_settings(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    path: PathBuf,
+    include_secrets: Option<bool>) -> Result<ExportResult, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    let settings = settings_manager.get_stored().await?;
+    let profiles = settings_manager.get_profiles().await?;
+
+    let (value, stripped_fields) = settings_transfer::export(
+        &settings,
+        &profiles.active_profile,
+        include_secrets.unwrap_or_default())?;
+
+    tokio::fs::write(&path, serde_json::to_vec_pretty(&value)?).await
+        .map_err(|err| AppError::Generic(Box::new(err)))?;
+
+    Ok(ExportResult {
+        path,
+        stripped_fields
+    })
+}
+
+#[command]
+pub async fn preview_settings_import(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    path: PathBuf) -> Result<ImportPreview, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    let settings = settings_manager.get_stored().await?;
+    let bytes = tokio::fs::read(&path).await
+        .map_err(|err| AppError::Generic(Box::new(err)))?;
+
+    Ok(settings_transfer::preview_import(&bytes, &settings)?)
+}
+
+/// Replaces the active profile with the settings of an exported file.
+#[command]
+pub async fn import_settings(
+    settings_manager: State<'_, Arc<Mutex<SettingsManager>>>,
+    path: PathBuf) -> Result<Settings, AppError> {
+
+    let mut settings_manager = settings_manager.lock().await;
+    let settings = settings_manager.get_stored().await?;
+    let bytes = tokio::fs::read(&path).await
+        .map_err(|err| AppError::Generic(Box::new(err)))?;
+
+    let preview = settings_transfer::preview_import(&bytes, &settings)?;
+
+    if !preview.field_errors.is_empty() {
+        return Err(AppError::Validation(preview.field_errors));
+    }
+
+    settings_manager.save(&preview.settings).await?;
+
+    Ok(preview.settings)
+}
+
+/// Restores the configured size and position of every window, e.g. when the monitor a window was on is gone.
+#[command]
+pub async fn reset_window_layout(app_handle: AppHandle) -> Result<(), AppError> {
+    window_layout::reset_window_layout(&app_handle)?;
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+
+use std::sync::Arc;
+use tauri::{command, State};
+use crate::{error::AppError, models::ShortcutStatus, shortcuts::ShortcutRegistry};
+
+/// Registration outcome of every bound shortcut, conflicts with other applications show up as `registered: false`.
+#[command]
+pub async fn get_shortcut_status(
+    shortcut_registry: State<'_, Arc<ShortcutRegistry>>) -> Result<Vec<ShortcutStatus>, AppError> {
+
+    Ok(shortcut_registry.statuses())
+}
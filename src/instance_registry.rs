@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::models::{GetInstancesResult, ProcessState, ProcessWatcherResult};
+
+/// Number of transitions kept for [`InstanceRegistry::history`].
+const HISTORY_SIZE: usize = 100;
+
+/// Latest watcher result of every live game client, the one the UI is showing and the
+/// most recent transitions.
+pub struct InstanceRegistry {
+    instances: Vec<ProcessWatcherResult>,
+    active_instance_id: Option<Uuid>,
+    watcher_result: ProcessWatcherResult,
+    history: VecDeque<ProcessWatcherResult>
+}
+
+impl InstanceRegistry {
+    pub fn new() -> Self {
+        Self {
+            instances: vec![],
+            active_instance_id: None,
+            watcher_result: ProcessWatcherResult {
+                instance_id: None,
+                checked_on: Utc::now(),
+                state: ProcessState::Unknown
+            },
+            history: VecDeque::with_capacity(HISTORY_SIZE)
+        }
+    }
+
+    pub fn update(&mut self, result: &ProcessWatcherResult) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(result.clone());
+
+        let Some(instance_id) = result.instance_id else {
+            self.watcher_result = result.clone();
+            return;
+        };
+
+        let is_stopped = matches!(result.state, ProcessState::ProcessStopped(_));
+        let mut result = result.clone();
+
+        // a reconnect is a one-off notification, the instance keeps listening on the new endpoint
+        if let ProcessState::ProcessReconnected(reconnect) = &result.state {
+            result.state = ProcessState::ProcessListening(reconnect.connection.clone());
+        }
+
+        let index = self.instances.iter().position(|instance| instance.instance_id == Some(instance_id));
+
+        match index {
+            Some(index) if is_stopped => {
+                self.instances.remove(index);
+            },
+            Some(index) => self.instances[index] = result,
+            None if !is_stopped => self.instances.push(result),
+            None => {},
+        }
+
+        let is_active_live = self.active_instance_id
+            .is_some_and(|active_instance_id| self.contains(active_instance_id));
+
+        if !is_active_live {
+            self.active_instance_id = self.instances.first().and_then(|instance| instance.instance_id);
+        }
+    }
+
+    /// Forgets every instance after the watcher has been restarted, the history is kept.
+    pub fn reset(&mut self) {
+        self.instances.clear();
+        self.active_instance_id = None;
+        self.watcher_result = ProcessWatcherResult {
+            instance_id: None,
+            checked_on: Utc::now(),
+            state: ProcessState::Unknown
+        };
+    }
+
+    pub fn set_active(&mut self, instance_id: Uuid) -> bool {
+        if !self.contains(instance_id) {
+            return false;
+        }
+
+        self.active_instance_id = Some(instance_id);
+        true
+    }
+
+    /// Latest result of every live instance, or the watcher state while no client is tracked.
+    pub fn current(&self) -> Vec<ProcessWatcherResult> {
+        if self.instances.is_empty() {
+            return vec![self.watcher_result.clone()];
+        }
+
+        self.instances.clone()
+    }
+
+    /// Up to `limit` most recent transitions, oldest first.
+    pub fn history(&self, limit: usize) -> Vec<ProcessWatcherResult> {
+        let skip = self.history.len().saturating_sub(limit);
+        self.history.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn active_instance_id(&self) -> Option<Uuid> {
+        self.active_instance_id
+    }
+
+    pub fn get(&self) -> GetInstancesResult {
+        GetInstancesResult {
+            active_instance_id: self.active_instance_id,
+            instances: self.instances.clone()
+        }
+    }
+
+    fn contains(&self, instance_id: Uuid) -> bool {
+        self.instances.iter().any(|instance| instance.instance_id == Some(instance_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{ConnectionInfo, ProcessInfo, ReconnectInfo, StoppedInfo};
+
+    use super::*;
+
+    fn process(pid: u32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            exe_path: None,
+            started_on: None,
+            first_seen_on: Utc::now()
+        }
+    }
+
+    fn connection(pid: u32, remote_ip: &str) -> ConnectionInfo {
+        ConnectionInfo {
+            process: process(pid),
+            remote_ip: remote_ip.parse().unwrap(),
+            remote_port: 6040,
+            region: "EUC".into(),
+            connected_on: Utc::now()
+        }
+    }
+
+    fn result(instance_id: Option<Uuid>, state: ProcessState) -> ProcessWatcherResult {
+        ProcessWatcherResult {
+            instance_id,
+            checked_on: Utc::now(),
+            state
+        }
+    }
+
+    #[test]
+    fn should_keep_last_transitions_only() {
+        let mut registry = InstanceRegistry::new();
+
+        for pid in 0..HISTORY_SIZE as u32 + 5 {
+            registry.update(&result(None, ProcessState::ProcessRunning(process(pid))));
+        }
+
+        let history = registry.history(usize::MAX);
+
+        let ProcessState::ProcessRunning(oldest) = &history[0].state else {
+            panic!("expected running state, got {:?}", history[0]);
+        };
+
+        assert_eq!(history.len(), HISTORY_SIZE);
+        assert_eq!(oldest.pid, 5);
+        assert_eq!(registry.history(2).len(), 2);
+    }
+
+    #[test]
+    fn should_keep_listening_after_reconnect_and_forget_stopped_instance() {
+        let mut registry = InstanceRegistry::new();
+        let first = Uuid::now_v7();
+        let second = Uuid::now_v7();
+        let previous = connection(1234, "127.0.0.1");
+        let reconnected = connection(1234, "127.0.0.2");
+
+        registry.update(&result(Some(first), ProcessState::ProcessListening(previous.clone())));
+        registry.update(&result(Some(second), ProcessState::ProcessNotListening(process(5678))));
+        registry.update(&result(Some(first), ProcessState::ProcessReconnected(ReconnectInfo {
+            previous,
+            connection: reconnected.clone()
+        })));
+
+        let current = registry.current();
+        assert_eq!(current.len(), 2);
+        assert_eq!(current[0].state, ProcessState::ProcessListening(reconnected));
+        assert_eq!(registry.active_instance_id(), Some(first));
+
+        registry.update(&result(Some(first), ProcessState::ProcessStopped(StoppedInfo {
+            process: process(1234),
+            stopped_on: Utc::now()
+        })));
+
+        let current = registry.current();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].instance_id, Some(second));
+        assert_eq!(registry.active_instance_id(), Some(second));
+    }
+
+    #[test]
+    fn should_forget_instances_but_keep_history_on_reset() {
+        let mut registry = InstanceRegistry::new();
+        let instance_id = Uuid::now_v7();
+
+        registry.update(&result(Some(instance_id), ProcessState::ProcessNotListening(process(1234))));
+        registry.reset();
+
+        let current = registry.current();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].instance_id, None);
+        assert_eq!(current[0].state, ProcessState::Unknown);
+        assert_eq!(registry.active_instance_id(), None);
+        assert!(!registry.set_active(instance_id));
+        assert_eq!(registry.history(usize::MAX).len(), 1);
+    }
+}
@@ -2,8 +2,13 @@
 
 use handlers::generate_handlers;
 use tauri::generate_context;
-use tauri_plugin_log::{Target, TargetKind};
 use tauri::Context;
+use app_paths::AppPaths;
+use autostart::autostart_args;
+use logging::{build_log_plugin, read_saved_logging};
+use models::LoggingSettings;
+use settings_overrides::SettingsOverrides;
+use window_layout::WINDOW_STATE_FLAGS;
 mod handlers;
 mod setup;
 mod hook;
@@ -13,32 +18,49 @@ mod models;
 mod aws_iprange;
 mod processor;
 mod app_ready_state;
+mod app_paths;
+mod autostart;
 mod settings_manager;
+mod settings_migration;
+mod merge_patch;
+mod settings_transfer;
+mod settings_overrides;
+mod settings_file_watcher;
+mod shortcuts;
 mod background_worker;
+mod class_registry;
 mod error;
 mod fake_encounter;
+mod instance_registry;
+mod logging;
+mod stop_signal;
+mod tray;
+mod window_layout;
 
 pub fn run() {
     hook::set_hook();
 
     let context: Context = generate_context!();
+    let overrides = SettingsOverrides::from_env();
+    let data_dir = overrides.data_dir.as_ref().map(|data_dir| data_dir.value.as_path());
+    let logging = AppPaths::settings_file_before_start(data_dir, &context.config().identifier)
+        .map_or_else(LoggingSettings::default, |settings_file| read_saved_logging(&settings_file));
+    let log_dir = data_dir.map(|data_dir| AppPaths::under(data_dir.to_path_buf()).log_dir);
 
     tauri::Builder::default()
-            .plugin(tauri_plugin_log::Builder::new()
-            .level(log::LevelFilter::Debug)
-            .targets([
-                Target::new(TargetKind::Stdout),
-                Target::new(TargetKind::LogDir {
-                    file_name: Some("logs".to_string()),
-                })
-            ])
-            .build())
+        .plugin(build_log_plugin(log_dir, &logging))
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_autostart::Builder::new()
+            .args(autostart_args(data_dir))
+            .build())
+        .plugin(tauri_plugin_window_state::Builder::default()
+            .with_state_flags(WINDOW_STATE_FLAGS)
+            .build())
         .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_single_instance::init(|_, _, _| {}))
+        .plugin(tauri_plugin_single_instance::init(|app_handle, _, _| tray::show_main_window(app_handle)))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .invoke_handler(generate_handlers())
-        .setup(setup::setup_app)
+        .setup(move |app| setup::setup_app(app, overrides))
         .run(context)
         .expect("error while running tauri application");
 }
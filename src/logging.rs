@@ -0,0 +1,210 @@
+use std::{fs, path::{Path, PathBuf}, sync::RwLock, time::{Duration, SystemTime}};
+
+use anyhow::Result;
+use log::*;
+use tauri::{plugin::TauriPlugin, Wry};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::{models::{LoggingSettings, SettingsFile}, settings_manager};
+
+/// Name of the current log file in the log directory, rotated files get a date appended.
+pub const LOG_FILE_NAME: &str = "logs";
+
+/// Levels consulted for every record, the log plugin itself lets everything through.
+static LOG_FILTER: RwLock<LogFilter> = RwLock::new(LogFilter {
+    level: LevelFilter::Debug,
+    modules: Vec::new()
+});
+
+#[derive(Debug, Clone, PartialEq)]
+struct LogFilter {
+    level: LevelFilter,
+    /// Sorted by descending length so the most specific module comes first.
+    modules: Vec<(String, LevelFilter)>
+}
+
+impl LogFilter {
+    fn new(logging: &LoggingSettings) -> Self {
+        let mut modules: Vec<_> = logging.modules
+            .iter()
+            .map(|(module, level)| (module.trim().to_string(), level.to_level_filter()))
+            .collect();
+        modules.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+
+        Self {
+            level: logging.level.to_level_filter(),
+            modules
+        }
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target.strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, Ord::max)
+    }
+}
+
+/// Builds the log plugin, registered before the settings are loaded so nothing logged during
+/// startup is lost. Max file size and rotation are fixed for the lifetime of the app,
+/// pass the ones from [`read_saved_logging`].
+///
+/// `log_dir` replaces the platform log directory, e.g. under `--data-dir`.
+/// Registering the plugin resets the max level, call [`set_log_levels`] afterwards.
+pub fn build_log_plugin(log_dir: Option<PathBuf>, logging: &LoggingSettings) -> TauriPlugin<Wry> {
+    let file_name = Some(LOG_FILE_NAME.to_string());
+    let file_target = match log_dir {
+        Some(path) => TargetKind::Folder { path, file_name },
+        None => TargetKind::LogDir { file_name },
+    };
+
+    tauri_plugin_log::Builder::new()
+        .level(LevelFilter::Trace)
+        .filter(|metadata| metadata.level() <= LOG_FILTER.read().unwrap().level_for(metadata.target()))
+        .max_file_size(logging.max_file_size_mb as u128 * 1024 * 1024)
+        .rotation_strategy(RotationStrategy::KeepSome(logging.rotation_count))
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(file_target)
+        ])
+        .build()
+}
+
+/// Logging settings of the active profile in `settings_file`, read before the logger exists.
+/// Defaults are used when the file is missing or invalid, loading the settings reports why later on.
+pub fn read_saved_logging(settings_file: &Path) -> LoggingSettings {
+    fs::read(settings_file).ok()
+        .and_then(|bytes| settings_manager::parse(&bytes).ok())
+        .and_then(|(value, _)| serde_json::from_value::<SettingsFile>(value).ok())
+        .and_then(|file| file.active_settings().map(|settings| settings.logging.clone()))
+        .unwrap_or_default()
+}
+
+/// Applies the level and module overrides of `logging` to every following record.
+pub fn set_log_levels(logging: &LoggingSettings) {
+    let filter = LogFilter::new(logging);
+    log::set_max_level(filter.max_level());
+    *LOG_FILTER.write().unwrap() = filter;
+}
+
+/// Deletes log files last written more than `retention_days` ago, returns how many were removed.
+pub fn prune_logs(log_dir: &Path, retention_days: u32) -> Result<usize> {
+    let retention = Duration::from_secs(retention_days as u64 * 24 * 60 * 60);
+    let mut removed = 0;
+
+    for entry in fs::read_dir(log_dir)? {
+        let path = entry?.path();
+
+        let is_log = path.extension().is_some_and(|extension| extension == "log");
+        let is_current = path.file_stem().is_some_and(|stem| stem == LOG_FILE_NAME);
+
+        if !is_log || is_current {
+            continue;
+        }
+
+        let age = fs::metadata(&path)?
+            .modified()?
+            .elapsed()
+            .unwrap_or_default();
+
+        if age > retention {
+            debug!("Removing old log file {:?}", path);
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Last `count` lines of the current log file, oldest first.
+pub async fn read_recent_lines(log_dir: &Path, count: usize) -> Result<Vec<String>> {
+    let path = log_dir.join(format!("{}.log", LOG_FILE_NAME));
+
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    let contents = tokio::fs::read(&path).await?;
+    let contents = String::from_utf8_lossy(&contents);
+    let lines: Vec<_> = contents.lines().collect();
+    let skip = lines.len().saturating_sub(count);
+
+    Ok(lines[skip..].iter().map(|line| line.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs::File};
+
+    use uuid::Uuid;
+
+    use crate::models::LogLevel;
+
+    use super::*;
+
+    #[test]
+    fn should_prefer_most_specific_module() {
+        let filter = LogFilter::new(&LoggingSettings {
+            level: LogLevel::Info,
+            modules: BTreeMap::from([
+                ("lost_metrics_app_lib".to_string(), LogLevel::Warn),
+                ("lost_metrics_app_lib::process_watcher".to_string(), LogLevel::Trace)
+            ]),
+            ..Default::default()
+        });
+
+        assert_eq!(filter.level_for("lost_metrics_app_lib::process_watcher::tracker"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("lost_metrics_app_lib::setup"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("lost_metrics_app_library"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn should_read_saved_file_size_and_rotation() {
+        let settings_file = std::env::temp_dir().join(format!("lost-metrics-{}.json", Uuid::now_v7()));
+        let mut file: SettingsFile = serde_json::from_slice(include_bytes!("../default_settings.json")).unwrap();
+        let logging = &mut file.profiles.get_mut(&file.active_profile).unwrap().logging;
+        logging.max_file_size_mb = 50;
+        logging.rotation_count = 2;
+
+        assert_eq!(read_saved_logging(&settings_file), LoggingSettings::default());
+
+        fs::write(&settings_file, serde_json::to_vec(&file).unwrap()).unwrap();
+        let logging = read_saved_logging(&settings_file);
+
+        assert_eq!(logging.max_file_size_mb, 50);
+        assert_eq!(logging.rotation_count, 2);
+
+        fs::remove_file(settings_file).unwrap();
+    }
+
+    #[test]
+    fn should_prune_only_expired_rotated_logs() {
+        let log_dir = std::env::temp_dir().join(format!("lost-metrics-{}", Uuid::now_v7()));
+        fs::create_dir_all(&log_dir).unwrap();
+        let expired_on = SystemTime::now() - Duration::from_secs(3 * 24 * 60 * 60);
+
+        for file_name in ["logs.log", "logs_2025-01-01_00-00-00.log", "notes.txt"] {
+            File::create(log_dir.join(file_name)).unwrap().set_modified(expired_on).unwrap();
+        }
+        File::create(log_dir.join("logs_2025-01-02_00-00-00.log")).unwrap();
+
+        assert_eq!(prune_logs(&log_dir, 2).unwrap(), 1);
+        assert!(log_dir.join("logs.log").exists());
+        assert!(!log_dir.join("logs_2025-01-01_00-00-00.log").exists());
+        assert!(log_dir.join("logs_2025-01-02_00-00-00.log").exists());
+
+        fs::remove_dir_all(log_dir).unwrap();
+    }
+}
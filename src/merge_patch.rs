@@ -0,0 +1,68 @@
+use serde_json::{Map, Value};
+
+/// Applies a JSON merge patch (RFC 7396): objects are merged recursively, `null` removes
+/// a member and any other value, arrays included, replaces the target.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+
+    let Value::Object(target) = target else {
+        unreachable!();
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+            continue;
+        }
+
+        merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_apply_rfc_example() {
+        let mut target = json!({
+            "title": "Goodbye!",
+            "author": { "givenName": "John", "familyName": "Doe" },
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged"
+        });
+
+        merge_patch(&mut target, &json!({
+            "title": "Hello!",
+            "phoneNumber": "+01-123-456-7890",
+            "author": { "familyName": null },
+            "tags": ["example"]
+        }));
+
+        assert_eq!(target, json!({
+            "title": "Hello!",
+            "author": { "givenName": "John" },
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890"
+        }));
+    }
+
+    #[test]
+    fn should_replace_non_object_target() {
+        let mut target = json!({ "a": "b" });
+
+        merge_patch(&mut target, &json!({ "a": { "bb": { "ccc": null } } }));
+
+        assert_eq!(target, json!({ "a": { "bb": {} } }));
+    }
+}
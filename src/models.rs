@@ -1,19 +1,66 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, net::IpAddr, path::PathBuf, str::FromStr, time::Duration};
 
 use chrono::{Date, DateTime, Utc};
-use semver::{Version, VersionReq};
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use tauri_plugin_global_shortcut::Shortcut;
 use uuid::Uuid;
 
+use crate::{class_registry, process_watcher::ProcessMatcher, settings_migration::CURRENT_SCHEMA_VERSION};
+
+/// State of the game client as seen by the process watcher.
+///
+/// Serialized adjacently tagged: `{ "type": "ProcessListening", "message": { ... } }`,
+/// where `message` is omitted for `Unknown` and `ProcessNotRunning`, holds a
+/// [`ProcessInfo`] for `ProcessRunning` and `ProcessNotListening`, a [`ConnectionInfo`]
+/// for `ProcessListening`, a [`ReconnectInfo`] for `ProcessReconnected` and a
+/// [`StoppedInfo`] for `ProcessStopped`.
+///
+/// `ProcessReconnected` is reported once when the remote endpoint of a live client changes,
+/// e.g. on a channel change; the client is listening on the new endpoint afterwards.
 #[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(tag = "type", content = "message")]
 pub enum ProcessState {
     Unknown,
     ProcessNotRunning,
-    ProcessRunning,
-    ProcessNotListening,
-    ProcessListening(String),
-    ProcesStopped
+    ProcessRunning(ProcessInfo),
+    ProcessNotListening(ProcessInfo),
+    ProcessListening(ConnectionInfo),
+    ProcessReconnected(ReconnectInfo),
+    ProcessStopped(StoppedInfo)
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub exe_path: Option<PathBuf>,
+    pub started_on: Option<DateTime<Utc>>,
+    pub first_seen_on: DateTime<Utc>
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionInfo {
+    pub process: ProcessInfo,
+    pub remote_ip: IpAddr,
+    pub remote_port: u16,
+    pub region: String,
+    pub connected_on: DateTime<Utc>
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconnectInfo {
+    pub previous: ConnectionInfo,
+    pub connection: ConnectionInfo
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoppedInfo {
+    pub process: ProcessInfo,
+    pub stopped_on: DateTime<Utc>
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -25,33 +72,731 @@ pub enum UpdaterState {
     Error(String)
 }
 
+/// State change of one game client instance, `instance_id` is `None` for states
+/// that are not tied to a client such as `ProcessNotRunning`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessEvent {
+    pub instance_id: Option<Uuid>,
+    pub state: ProcessState
+}
+
+/// Message sent from the process watcher thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatcherEvent {
+    State(ProcessEvent),
+    ConnectionSample {
+        instance_id: Uuid,
+        sample: ConnectionSample
+    }
+}
+
+/// TCP metrics of the game connection at one point in time.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionSample {
+    pub sampled_on: DateTime<Utc>,
+    pub rtt_ms: f64,
+    pub rtt_var_ms: f64,
+    /// Total retransmitted segments over the lifetime of the socket.
+    pub retransmits: u64
+}
+
+/// Connection metrics aggregated over an encounter.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionQuality {
+    pub samples: u32,
+    pub avg_rtt_ms: f64,
+    pub min_rtt_ms: f64,
+    pub max_rtt_ms: f64,
+    pub last_rtt_ms: f64,
+    /// Segments retransmitted since the first sample of the encounter.
+    pub retransmits: u64,
+    #[serde(skip)]
+    baseline_retransmits: Option<u64>
+}
+
+impl ConnectionQuality {
+    pub fn record(&mut self, sample: &ConnectionSample) {
+        let baseline_retransmits = *self.baseline_retransmits.get_or_insert(sample.retransmits);
+        self.retransmits = sample.retransmits.saturating_sub(baseline_retransmits);
+
+        if self.samples == 0 {
+            self.min_rtt_ms = sample.rtt_ms;
+            self.max_rtt_ms = sample.rtt_ms;
+        } else {
+            self.min_rtt_ms = self.min_rtt_ms.min(sample.rtt_ms);
+            self.max_rtt_ms = self.max_rtt_ms.max(sample.rtt_ms);
+        }
+
+        self.avg_rtt_ms = (self.avg_rtt_ms * self.samples as f64 + sample.rtt_ms) / (self.samples + 1) as f64;
+        self.last_rtt_ms = sample.rtt_ms;
+        self.samples += 1;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionQualityResult {
+    pub instance_id: Uuid,
+    pub sample: ConnectionSample,
+    pub quality: ConnectionQuality
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessWatcherResult {
+    pub instance_id: Option<Uuid>,
     pub checked_on: DateTime<Utc>,
     pub state: ProcessState
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherError {
+    pub message: String,
+    pub occurred_on: DateTime<Utc>
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessWatcherStatus {
+    pub is_alive: bool,
+    pub last_error: Option<WatcherError>,
+    pub active_instance_id: Option<Uuid>,
+    pub current: Vec<ProcessWatcherResult>,
+    pub history: Vec<ProcessWatcherResult>
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetInstancesResult {
+    pub active_instance_id: Option<Uuid>,
+    pub instances: Vec<ProcessWatcherResult>
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct UpdaterResult {
     pub checked_on: DateTime<Utc>,
     pub state: UpdaterState
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// Process name pattern, serialized as `{ "type": "glob", "value": "client_*.exe" }`.
+///
+/// Globs support `*` and `?`; globs and regexes have to match the whole name or path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum ProcessPattern {
+    Exact(String),
+    Glob(String),
+    Regex(String)
+}
+
+impl ProcessPattern {
+    pub fn value(&self) -> &str {
+        match self {
+            ProcessPattern::Exact(value)
+            | ProcessPattern::Glob(value)
+            | ProcessPattern::Regex(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SnifferSettings {
-    pub process_name: String,
+    pub process_names: Vec<ProcessPattern>,
+    /// Matches names case-sensitively, Windows executable names usually differ in case only.
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Also matches the patterns against the full executable path.
+    #[serde(default)]
+    pub match_executable_path: bool,
     pub port: u16,
     #[serde(with = "humantime_serde")]
     pub check_interval: Duration
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+impl SnifferSettings {
+    pub const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+    pub const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Collects every rule violation, fields are prefixed with `path`.
+    pub fn validate(&self, path: &str) -> Vec<FieldError> {
+        let mut errors = vec![];
+        let process_names_path = format!("{}.processNames", path);
+
+        if self.process_names.is_empty() {
+            errors.push(FieldError::new(&process_names_path, "at least one process name is required"));
+        }
+
+        for (index, pattern) in self.process_names.iter().enumerate() {
+            let field = format!("{}[{}]", process_names_path, index);
+
+            if pattern.value().trim().is_empty() {
+                errors.push(FieldError::new(&field, "process name must not be empty"));
+            } else if let Err(err) = ProcessMatcher::check_pattern(pattern) {
+                errors.push(FieldError::new(&field, format!("{:#}", err)));
+            }
+        }
+
+        if self.port == 0 {
+            errors.push(FieldError::new(&format!("{}.port", path), "port must not be 0"));
+        }
+
+        if self.check_interval < Self::MIN_CHECK_INTERVAL || self.check_interval > Self::MAX_CHECK_INTERVAL {
+            errors.push(FieldError::new(
+                &format!("{}.checkInterval", path),
+                format!("check interval must be between {:?} and {:?}", Self::MIN_CHECK_INTERVAL, Self::MAX_CHECK_INTERVAL)));
+        }
+
+        errors
+    }
+}
+
+/// Rule violation of a single settings input, `field` is the camelCase JSON path, e.g. `sniffer.port`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into()
+        }
+    }
+}
+
+/// Meter action that can be bound to a global shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    ToggleOverlay,
+    ResetEncounter,
+    ToggleClickThrough,
+    CopySummary
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 4] = [
+        ShortcutAction::ToggleOverlay,
+        ShortcutAction::ResetEncounter,
+        ShortcutAction::ToggleClickThrough,
+        ShortcutAction::CopySummary
+    ];
+
+    /// camelCase name, as used in `settings.json` and field errors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShortcutAction::ToggleOverlay => "toggleOverlay",
+            ShortcutAction::ResetEncounter => "resetEncounter",
+            ShortcutAction::ToggleClickThrough => "toggleClickThrough",
+            ShortcutAction::CopySummary => "copySummary",
+        }
+    }
+}
+
+/// Key combinations of the global shortcuts, e.g. `Alt+Shift+M`, `None` leaves the action unbound.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutSettings {
+    pub toggle_overlay: Option<String>,
+    pub reset_encounter: Option<String>,
+    pub toggle_click_through: Option<String>,
+    pub copy_summary: Option<String>
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            toggle_overlay: Some("Alt+Shift+M".into()),
+            reset_encounter: Some("Alt+Shift+R".into()),
+            toggle_click_through: Some("Alt+Shift+T".into()),
+            copy_summary: Some("Alt+Shift+C".into())
+        }
+    }
+}
+
+impl ShortcutSettings {
+    pub fn get(&self, action: ShortcutAction) -> Option<&str> {
+        let shortcut = match action {
+            ShortcutAction::ToggleOverlay => &self.toggle_overlay,
+            ShortcutAction::ResetEncounter => &self.reset_encounter,
+            ShortcutAction::ToggleClickThrough => &self.toggle_click_through,
+            ShortcutAction::CopySummary => &self.copy_summary,
+        };
+
+        shortcut.as_deref().map(str::trim).filter(|shortcut| !shortcut.is_empty())
+    }
+
+    /// Bound actions with their key combinations.
+    pub fn bindings(&self) -> Vec<(ShortcutAction, &str)> {
+        ShortcutAction::ALL
+            .into_iter()
+            .filter_map(|action| self.get(action).map(|shortcut| (action, shortcut)))
+            .collect()
+    }
+
+    /// Collects unparsable key combinations and actions bound to the same combination.
+    pub fn validate(&self, path: &str) -> Vec<FieldError> {
+        let mut errors = vec![];
+        let mut bound: Vec<(ShortcutAction, Shortcut)> = vec![];
+
+        for (action, shortcut) in self.bindings() {
+            let field = format!("{}.{}", path, action.name());
+
+            let shortcut = match Shortcut::from_str(shortcut) {
+                Ok(shortcut) => shortcut,
+                Err(err) => {
+                    errors.push(FieldError::new(&field, format!("invalid shortcut: {}", err)));
+                    continue;
+                },
+            };
+
+            match bound.iter().find(|(_, other)| *other == shortcut) {
+                Some((other, _)) => errors.push(FieldError::new(&field, format!("shortcut is already used by {}", other.name()))),
+                None => bound.push((action, shortcut)),
+            }
+        }
+
+        errors
+    }
+}
+
+/// Registration outcome of a single global shortcut.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutStatus {
+    pub action: ShortcutAction,
+    pub shortcut: String,
+    pub registered: bool,
+    /// Why registration failed, usually another application holding the same combination.
+    pub error: Option<String>
+}
+
+/// Launch at login, registered with the OS through the autostart plugin.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutostartSettings {
+    pub enabled: bool,
+    /// Keeps the main window hidden in the tray when launched at login.
+    pub start_minimized: bool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace
+}
+
+impl LogLevel {
+    pub fn to_level_filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Levels apply immediately. File size and rotation are read from the saved settings when the log plugin
+/// is registered, changes take effect on the next start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingSettings {
+    pub level: LogLevel,
+    /// Levels of single modules, e.g. `lost_metrics_app_lib::process_watcher`, the longest match wins.
+    pub modules: BTreeMap<String, LogLevel>,
+    pub max_file_size_mb: u32,
+    /// Rotated files kept next to the current one.
+    pub rotation_count: usize,
+    /// Log files not written to for this many days are deleted on startup.
+    pub retention_days: u32
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Debug,
+            modules: BTreeMap::new(),
+            max_file_size_mb: 10,
+            rotation_count: 5,
+            retention_days: 14
+        }
+    }
+}
+
+impl LoggingSettings {
+    pub const MAX_FILE_SIZE_MB: u32 = 1024;
+    pub const MAX_ROTATION_COUNT: usize = 100;
+
+    pub fn validate(&self, path: &str) -> Vec<FieldError> {
+        let mut errors = vec![];
+
+        if self.modules.keys().any(|module| module.trim().is_empty()) {
+            errors.push(FieldError::new(&format!("{}.modules", path), "module name must not be empty"));
+        }
+
+        if self.max_file_size_mb == 0 || self.max_file_size_mb > Self::MAX_FILE_SIZE_MB {
+            errors.push(FieldError::new(
+                &format!("{}.maxFileSizeMb", path),
+                format!("max file size must be between 1 and {} MB", Self::MAX_FILE_SIZE_MB)));
+        }
+
+        if self.rotation_count == 0 || self.rotation_count > Self::MAX_ROTATION_COUNT {
+            errors.push(FieldError::new(
+                &format!("{}.rotationCount", path),
+                format!("rotation count must be between 1 and {}", Self::MAX_ROTATION_COUNT)));
+        }
+
+        if self.retention_days == 0 {
+            errors.push(FieldError::new(&format!("{}.retentionDays", path), "retention must be at least 1 day"));
+        }
+
+        errors
+    }
+}
+
+/// Options of a window the window-state plugin doesn't keep, position and size are restored by the plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowSettings {
+    pub always_on_top: bool,
+    /// Applied by the frontend of the window, from [`WindowSettings::MIN_OPACITY`] to 1.
+    pub opacity: f64
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            always_on_top: true,
+            opacity: 1.0
+        }
+    }
+}
+
+impl WindowSettings {
+    pub const MIN_OPACITY: f64 = 0.2;
+
+    pub fn validate(&self, path: &str) -> Vec<FieldError> {
+        let mut errors = vec![];
+
+        if !(Self::MIN_OPACITY..=1.0).contains(&self.opacity) {
+            errors.push(FieldError::new(
+                &format!("{}.opacity", path),
+                format!("opacity must be between {} and 1", Self::MIN_OPACITY)));
+        }
+
+        errors
+    }
+}
+
+fn default_windows() -> BTreeMap<String, WindowSettings> {
+    BTreeMap::from([("main".to_string(), WindowSettings::default())])
+}
+
+/// Value shown in a meter column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MeterColumn {
+    Damage,
+    Dps,
+    DamageShare,
+    CritRate,
+    FrontAttackRate,
+    BackAttackRate,
+    Deaths
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterSort {
+    pub column: MeterColumn,
+    pub descending: bool
+}
+
+/// How players are labelled in the meter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NameDisplay {
+    Name,
+    Class,
+    NameAndClass
+}
+
+/// Shared by every window so meters render the same way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplaySettings {
+    /// `#rrggbb` colours keyed by class id, replacing the defaults of [`crate::class_registry`].
+    pub class_colors: BTreeMap<u32, String>,
+    /// Visible columns, in display order.
+    pub columns: Vec<MeterColumn>,
+    pub sort: MeterSort,
+    pub name_display: NameDisplay
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            class_colors: BTreeMap::new(),
+            columns: vec![MeterColumn::Damage, MeterColumn::Dps, MeterColumn::DamageShare, MeterColumn::CritRate],
+            sort: MeterSort {
+                column: MeterColumn::Dps,
+                descending: true
+            },
+            name_display: NameDisplay::NameAndClass
+        }
+    }
+}
+
+impl DisplaySettings {
+    pub fn validate(&self, path: &str) -> Vec<FieldError> {
+        let mut errors = vec![];
+
+        for (class_id, color) in &self.class_colors {
+            let field = format!("{}.classColors.{}", path, class_id);
+
+            if class_registry::find_class(*class_id).is_none() {
+                errors.push(FieldError::new(&field, format!("unknown class {}", class_id)));
+            } else if !is_hex_color(color) {
+                errors.push(FieldError::new(&field, "color must be in the form #rrggbb"));
+            }
+        }
+
+        let columns_path = format!("{}.columns", path);
+
+        if self.columns.is_empty() {
+            errors.push(FieldError::new(&columns_path, "at least one column is required"));
+        }
+
+        for (index, column) in self.columns.iter().enumerate() {
+            if self.columns[..index].contains(column) {
+                errors.push(FieldError::new(&format!("{}[{}]", columns_path, index), "column is already shown"));
+            }
+        }
+
+        if !self.columns.contains(&self.sort.column) {
+            errors.push(FieldError::new(&format!("{}.sort.column", path), "sort column must be visible"));
+        }
+
+        errors
+    }
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|char| char.is_ascii_hexdigit()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClassRole {
+    Dps,
+    Support
+}
+
+/// Class of the registry with the colour the meter should use.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassInfo {
+    pub id: u32,
+    pub name: String,
+    pub role: ClassRole,
+    /// Override from [`DisplaySettings::class_colors`], or `default_color`.
+    pub color: String,
+    pub default_color: String
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetClassRegistryResult {
+    pub classes: Vec<ClassInfo>,
+    pub display: DisplaySettings
+}
+
+/// Settings of a single profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Settings {
-    pub version: VersionReq,
-    pub sniffer: SnifferSettings
+    pub sniffer: SnifferSettings,
+    #[serde(default)]
+    pub shortcuts: ShortcutSettings,
+    #[serde(default)]
+    pub autostart: AutostartSettings,
+    /// Keyed by window label.
+    #[serde(default = "default_windows")]
+    pub windows: BTreeMap<String, WindowSettings>,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    #[serde(default)]
+    pub display: DisplaySettings
+}
+
+// by hand so the defaults match what serde fills in for a missing `windows`
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sniffer: SnifferSettings::default(),
+            shortcuts: ShortcutSettings::default(),
+            autostart: AutostartSettings::default(),
+            windows: default_windows(),
+            logging: LoggingSettings::default(),
+            display: DisplaySettings::default()
+        }
+    }
+}
+
+impl Settings {
+    /// Paths of fields left out of exports unless asked for, e.g. `integrations.apiToken`.
+    /// Nothing in the settings is secret yet, new credentials have to be listed here.
+    pub const SECRET_FIELDS: &'static [&'static str] = &[];
+
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = self.sniffer.validate("sniffer");
+        errors.extend(self.shortcuts.validate("shortcuts"));
+
+        for (label, window) in &self.windows {
+            errors.extend(window.validate(&format!("windows.{}", label)));
+        }
+
+        errors.extend(self.logging.validate("logging"));
+        errors.extend(self.display.validate("display"));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Contents of `settings.json`, every profile holds a complete [`Settings`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsFile {
+    /// Schema of the settings file, older files are upgraded by [`crate::settings_migration`].
+    pub schema_version: u32,
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Settings>
+}
+
+impl SettingsFile {
+    pub const MAX_PROFILE_NAME_LEN: usize = 64;
+
+    pub fn active_settings(&self) -> Option<&Settings> {
+        self.profiles.get(&self.active_profile)
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = vec![];
+
+        if self.schema_version > CURRENT_SCHEMA_VERSION {
+            errors.push(FieldError::new("schemaVersion", format!("schema {} is not supported", self.schema_version)));
+        }
+
+        if !self.profiles.contains_key(&self.active_profile) {
+            errors.push(FieldError::new("activeProfile", format!("profile {:?} does not exist", self.active_profile)));
+        }
+
+        for (name, settings) in &self.profiles {
+            if let Err(profile_errors) = settings.validate() {
+                errors.extend(profile_errors.into_iter().map(|error| FieldError {
+                    field: format!("profiles.{}.{}", name, error.field),
+                    ..error
+                }));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn profiles(&self) -> ProfilesResult {
+        ProfilesResult {
+            active_profile: self.active_profile.clone(),
+            profiles: self.profiles.keys().cloned().collect()
+        }
+    }
+}
+
+/// Payload of `settings-file-invalid`, emitted when a hand-edited settings file is rejected.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsFileError {
+    pub path: PathBuf,
+    pub message: String,
+    pub occurred_on: DateTime<Utc>
+}
+
+/// Where the effective value of a setting comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingSource {
+    File,
+    Environment,
+    CommandLine
+}
+
+/// Effective settings of the active profile and where overridable values come from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSettingsResult {
+    #[serde(flatten)]
+    pub settings: Settings,
+    pub data_dir: PathBuf,
+    pub sources: BTreeMap<String, SettingSource>
+}
+
+/// Settings found in an exported file, `changes` compares them to the active profile.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    /// Schema the file was exported with, older files are migrated.
+    pub schema_version: u32,
+    pub profile_name: String,
+    pub settings: Settings,
+    pub changes: Vec<SettingsChange>,
+    pub field_errors: Vec<FieldError>
+}
+
+/// Setting that differs, `None` where the member is missing on that side.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsChange {
+    pub field: String,
+    pub current: Option<serde_json::Value>,
+    pub imported: Option<serde_json::Value>
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub path: PathBuf,
+    /// Fields left out because they are listed in [`Settings::SECRET_FIELDS`].
+    pub stripped_fields: Vec<String>
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilesResult {
+    pub active_profile: String,
+    pub profiles: Vec<String>
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -60,6 +805,8 @@ pub struct LoadResult {
     pub app_name: String,
     pub github_link: String,
     pub version: String,
+    /// Settings problems found on startup, e.g. a corrupt file replaced by a backup.
+    pub warnings: Vec<String>
     // pub settings: Settings
 }
 
@@ -67,10 +814,12 @@ pub struct LoadResult {
 #[serde(rename_all = "camelCase")]
 pub struct Encounter {
     pub id: Uuid,
+    pub instance_id: Uuid,
     pub updated_on: DateTime<Utc>,
     pub participants: Vec<Player>,
     pub boss: Boss,
     pub total_damage: FormattedValue,
+    pub connection_quality: Option<ConnectionQuality>,
 }
 
 #[derive(Debug, Default, Clone, Serialize)]
@@ -209,3 +958,171 @@ pub struct Metric {
 pub struct GetPastEncountersCriteria {
     
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_collect_every_field_error() {
+        let settings = Settings {
+            sniffer: SnifferSettings {
+                process_names: vec![
+                    ProcessPattern::Exact("client_server.exe".into()),
+                    ProcessPattern::Glob(" ".into()),
+                    ProcessPattern::Regex("client_(".into())
+                ],
+                port: 0,
+                check_interval: Duration::ZERO,
+                ..Default::default()
+            },
+            shortcuts: ShortcutSettings {
+                toggle_overlay: Some("Alt+Shift+M".into()),
+                reset_encounter: None,
+                toggle_click_through: Some("Alt+Nope".into()),
+                copy_summary: Some("shift+alt+m".into())
+            },
+            ..Default::default()
+        };
+
+        let fields: Vec<_> = settings.validate().unwrap_err()
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+
+        assert_eq!(fields, vec![
+            "sniffer.processNames[1]",
+            "sniffer.processNames[2]",
+            "sniffer.port",
+            "sniffer.checkInterval",
+            "shortcuts.toggleClickThrough",
+            "shortcuts.copySummary"
+        ]);
+    }
+
+    #[test]
+    fn should_reject_invalid_sniffer_settings() {
+        let sniffer = SnifferSettings {
+            process_names: vec![],
+            port: 6040,
+            check_interval: SnifferSettings::MAX_CHECK_INTERVAL + Duration::from_millis(1),
+            ..Default::default()
+        };
+
+        assert_eq!(sniffer.validate("sniffer"), vec![
+            FieldError::new("sniffer.processNames", "at least one process name is required"),
+            FieldError::new("sniffer.checkInterval", "check interval must be between 100ms and 60s")
+        ]);
+
+        let sniffer = SnifferSettings {
+            process_names: vec![
+                ProcessPattern::Exact("".into()),
+                ProcessPattern::Regex("client_[".into())
+            ],
+            port: 0,
+            check_interval: SnifferSettings::MIN_CHECK_INTERVAL,
+            ..Default::default()
+        };
+
+        let errors = sniffer.validate("sniffer");
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0], FieldError::new("sniffer.processNames[0]", "process name must not be empty"));
+        assert_eq!(errors[1].field, "sniffer.processNames[1]");
+        assert_eq!(errors[2], FieldError::new("sniffer.port", "port must not be 0"));
+    }
+
+    #[test]
+    fn should_accept_check_interval_bounds() {
+        for check_interval in [SnifferSettings::MIN_CHECK_INTERVAL, SnifferSettings::MAX_CHECK_INTERVAL] {
+            let sniffer = SnifferSettings {
+                process_names: vec![ProcessPattern::Glob("client_*.exe".into())],
+                port: 6040,
+                check_interval,
+                ..Default::default()
+            };
+
+            assert!(sniffer.validate("sniffer").is_empty());
+        }
+    }
+
+    #[test]
+    fn should_validate_window_opacity() {
+        for opacity in [WindowSettings::MIN_OPACITY, 1.0] {
+            let window = WindowSettings { opacity, ..Default::default() };
+            assert!(window.validate("windows.main").is_empty());
+        }
+
+        for opacity in [0.1, 1.5, f64::NAN] {
+            let window = WindowSettings { opacity, ..Default::default() };
+            let fields: Vec<_> = window.validate("windows.main")
+                .into_iter()
+                .map(|error| error.field)
+                .collect();
+
+            assert_eq!(fields, vec!["windows.main.opacity"]);
+        }
+    }
+
+    #[test]
+    fn should_default_like_missing_sections() {
+        let settings = Settings::default();
+        let sniffer = serde_json::to_value(&settings.sniffer).unwrap();
+        let deserialized: Settings = serde_json::from_value(serde_json::json!({ "sniffer": sniffer })).unwrap();
+
+        assert_eq!(settings, deserialized);
+        assert!(settings.windows.contains_key("main"));
+    }
+
+    #[test]
+    fn should_validate_display_settings() {
+        let display = DisplaySettings {
+            class_colors: BTreeMap::from([(102, "#ee2e48".into()), (204, "purple".into()), (999, "#000000".into())]),
+            columns: vec![MeterColumn::Damage, MeterColumn::Dps, MeterColumn::Damage],
+            sort: MeterSort {
+                column: MeterColumn::Deaths,
+                descending: true
+            },
+            name_display: NameDisplay::Name
+        };
+
+        let fields: Vec<_> = display.validate("display")
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+
+        assert_eq!(fields, vec![
+            "display.classColors.204",
+            "display.classColors.999",
+            "display.columns[2]",
+            "display.sort.column"
+        ]);
+    }
+
+    #[test]
+    fn should_validate_every_section_of_every_profile() {
+        let mut file: SettingsFile = serde_json::from_slice(include_bytes!("../default_settings.json")).unwrap();
+        let mut raid = file.active_settings().unwrap().clone();
+        raid.logging.rotation_count = 0;
+        raid.shortcuts.copy_summary = Some("Alt+Nope".into());
+        file.profiles.insert("Raid".into(), raid);
+
+        let fields: Vec<_> = file.validate().unwrap_err()
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+
+        assert_eq!(fields, vec![
+            "profiles.Raid.shortcuts.copySummary",
+            "profiles.Raid.logging.rotationCount"
+        ]);
+    }
+
+    #[test]
+    fn should_accept_default_settings() {
+        let settings: SettingsFile = serde_json::from_slice(include_bytes!("../default_settings.json")).unwrap();
+
+        assert!(settings.validate().is_ok());
+        assert!(settings.active_settings().is_some());
+    }
+}
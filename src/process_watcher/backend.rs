@@ -0,0 +1,101 @@
+use std::{net::SocketAddr, sync::{Arc, Mutex}};
+
+use anyhow::Result;
+
+use crate::models::{ConnectionSample, ProcessInfo};
+
+use super::ProcessMatcher;
+
+/// Looks up the game client process.
+pub trait ProcessProbe: Send {
+    /// Looks up every running process accepted by the matcher, `first_seen_on` is the time of this lookup.
+    fn find_processes(&mut self, matcher: &ProcessMatcher) -> Vec<ProcessInfo>;
+
+    /// Checks whether a previously found process is still alive.
+    fn is_alive(&mut self, process_id: u32) -> bool;
+}
+
+/// Both ends of a TCP connection owned by the game client.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SocketPair {
+    pub local: SocketAddr,
+    pub remote: SocketAddr
+}
+
+/// Looks up the connections of the game client process.
+pub trait NetworkProbe: Send {
+    /// Called once at the start of every check, before any instance is probed.
+    fn refresh(&mut self) {}
+
+    /// Returns the TCP connections owned by the process on the given remote port.
+    fn find_connections(&mut self, process_id: u32, port: u16) -> Result<Vec<SocketPair>>;
+
+    /// Samples TCP metrics of the given connection, `None` where the platform offers none.
+    fn sample_connection(&mut self, socket: &SocketPair) -> Option<ConnectionSample> {
+        None
+    }
+}
+
+/// Probes used by the [`ProcessWatcher`](super::ProcessWatcher), created on the watcher thread.
+pub struct ProcessBackend {
+    pub process_probe: Box<dyn ProcessProbe>,
+    pub network_probe: Box<dyn NetworkProbe>
+}
+
+impl ProcessBackend {
+    /// Uses a single probe for processes and connections.
+    pub fn shared<P: ProcessProbe + NetworkProbe + 'static>(probe: P) -> Self {
+        let probe = Arc::new(Mutex::new(probe));
+
+        Self {
+            process_probe: Box::new(SharedProbe(probe.clone())),
+            network_probe: Box::new(SharedProbe(probe))
+        }
+    }
+}
+
+/// Handle of a probe shared by both halves of a [`ProcessBackend`], only ever locked from the watcher thread.
+struct SharedProbe<P>(Arc<Mutex<P>>);
+
+impl<P: ProcessProbe> ProcessProbe for SharedProbe<P> {
+    fn find_processes(&mut self, matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+        self.0.lock().unwrap().find_processes(matcher)
+    }
+
+    fn is_alive(&mut self, process_id: u32) -> bool {
+        self.0.lock().unwrap().is_alive(process_id)
+    }
+}
+
+impl<P: NetworkProbe> NetworkProbe for SharedProbe<P> {
+    fn refresh(&mut self) {
+        self.0.lock().unwrap().refresh()
+    }
+
+    fn find_connections(&mut self, process_id: u32, port: u16) -> Result<Vec<SocketPair>> {
+        self.0.lock().unwrap().find_connections(process_id, port)
+    }
+
+    fn sample_connection(&mut self, socket: &SocketPair) -> Option<ConnectionSample> {
+        self.0.lock().unwrap().sample_connection(socket)
+    }
+}
+
+pub type ProcessBackendFactory = Arc<dyn Fn() -> ProcessBackend + Send + Sync>;
+
+#[cfg(target_os = "linux")]
+pub fn default_backend() -> ProcessBackend {
+    use super::procfs_backend::ProcfsBackend;
+
+    ProcessBackend::shared(ProcfsBackend::new())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn default_backend() -> ProcessBackend {
+    use super::sysinfo_backend::{NetstatProbe, SysinfoBackend};
+
+    ProcessBackend {
+        process_probe: Box::new(SysinfoBackend::new()),
+        network_probe: Box::new(NetstatProbe)
+    }
+}
@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+
+use crate::models::{ProcessPattern, SnifferSettings};
+
+/// Compiled [`ProcessPattern`]s of the sniffer settings.
+#[derive(Debug, Clone)]
+pub struct ProcessMatcher {
+    patterns: Vec<CompiledPattern>,
+    case_sensitive: bool,
+    match_executable_path: bool
+}
+
+#[derive(Debug, Clone)]
+struct CompiledPattern {
+    regex: Regex,
+    /// Name of an exact pattern, used to match `comm` truncated by the kernel.
+    exact_name: Option<String>
+}
+
+impl ProcessMatcher {
+    pub fn new(settings: &SnifferSettings) -> Result<Self> {
+        let patterns = settings.process_names.iter()
+            .map(|pattern| CompiledPattern::new(pattern, settings.case_sensitive))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            patterns,
+            case_sensitive: settings.case_sensitive,
+            match_executable_path: settings.match_executable_path
+        })
+    }
+
+    /// Compiles a single pattern to report whether it is valid.
+    pub fn check_pattern(pattern: &ProcessPattern) -> Result<()> {
+        CompiledPattern::new(pattern, true).map(|_| ())
+    }
+
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.regex.is_match(name))
+    }
+
+    /// Matches a name cut to `max_len` bytes against the exact patterns only,
+    /// a truncated name cannot be told apart for globs and regexes.
+    pub fn matches_truncated_name(&self, name: &str, max_len: usize) -> bool {
+        if name.len() != max_len {
+            return false;
+        }
+
+        self.patterns.iter()
+            .filter_map(|pattern| pattern.exact_name.as_deref())
+            .any(|exact_name| match exact_name.get(..max_len) {
+                Some(prefix) if self.case_sensitive => prefix == name,
+                Some(prefix) => prefix.eq_ignore_ascii_case(name),
+                None => false,
+            })
+    }
+
+    /// Whether [`ProcessMatcher::matches_path`] can match at all, lets backends skip reading the path.
+    pub fn wants_path(&self) -> bool {
+        self.match_executable_path
+    }
+
+    /// Matches the full executable path when enabled in the settings, separators normalized to `/`.
+    pub fn matches_path(&self, path: &Path) -> bool {
+        if !self.wants_path() {
+            return false;
+        }
+
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.patterns.iter().any(|pattern| pattern.regex.is_match(&path))
+    }
+}
+
+impl CompiledPattern {
+    fn new(pattern: &ProcessPattern, case_sensitive: bool) -> Result<Self> {
+        let (expression, exact_name) = match pattern {
+            ProcessPattern::Exact(name) => (regex::escape(name), Some(name.clone())),
+            ProcessPattern::Glob(glob) => (glob_to_regex(glob), None),
+            ProcessPattern::Regex(regex) => (regex.clone(), None),
+        };
+
+        let regex = RegexBuilder::new(&format!("^(?:{})$", expression))
+            .case_insensitive(!case_sensitive)
+            .build()
+            .with_context(|| format!("invalid process name pattern {:?}", pattern.value()))?;
+
+        Ok(Self {
+            regex,
+            exact_name
+        })
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut expression = String::with_capacity(glob.len() * 2);
+
+    for char in glob.chars() {
+        match char {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            char => expression.push_str(&regex::escape(char.encode_utf8(&mut [0; 4]))),
+        }
+    }
+
+    expression
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn matcher(process_names: Vec<ProcessPattern>, case_sensitive: bool, match_executable_path: bool) -> ProcessMatcher {
+        ProcessMatcher::new(&SnifferSettings {
+            process_names,
+            case_sensitive,
+            match_executable_path,
+            port: 6040,
+            check_interval: Duration::from_secs(1)
+        }).unwrap()
+    }
+
+    #[test]
+    fn should_match_names_case_insensitively() {
+        let matcher = matcher(vec![
+            ProcessPattern::Exact("client_server.exe".into()),
+            ProcessPattern::Glob("lost?ark*.exe".into()),
+            ProcessPattern::Regex(r"launcher_\d+\.exe".into())
+        ], false, false);
+
+        assert!(matcher.matches_name("Client_Server.exe"));
+        assert!(matcher.matches_name("LOST_ARK_dx12.exe"));
+        assert!(matcher.matches_name("launcher_64.exe"));
+        assert!(!matcher.matches_name("client_server.exe.bak"));
+        assert!(!matcher.matches_name("launcher_x.exe"));
+    }
+
+    #[test]
+    fn should_respect_case_sensitivity() {
+        let matcher = matcher(vec![ProcessPattern::Exact("client_server.exe".into())], true, false);
+
+        assert!(matcher.matches_name("client_server.exe"));
+        assert!(!matcher.matches_name("Client_Server.exe"));
+    }
+
+    #[test]
+    fn should_match_truncated_comm_of_exact_names_only() {
+        let matcher = matcher(vec![
+            ProcessPattern::Exact("client_server.exe".into()),
+            ProcessPattern::Glob("launcher_*.exe".into())
+        ], false, false);
+
+        assert!(matcher.matches_truncated_name("CLIENT_SERVER.E", 15));
+        assert!(!matcher.matches_truncated_name("launcher_dx12.e", 15));
+        assert!(!matcher.matches_truncated_name("client_server", 15));
+    }
+
+    #[test]
+    fn should_match_executable_path_when_enabled() {
+        let patterns = vec![ProcessPattern::Glob("*/Lost Ark/Binaries/*.exe".into())];
+        let path = Path::new(r"C:\Games\Lost Ark\Binaries\renamed.exe");
+
+        assert!(matcher(patterns.clone(), false, true).wants_path());
+        assert!(matcher(patterns.clone(), false, true).matches_path(path));
+        assert!(!matcher(patterns.clone(), false, false).wants_path());
+        assert!(!matcher(patterns, false, false).matches_path(path));
+    }
+
+    #[test]
+    fn should_reject_invalid_regex() {
+        let settings = SnifferSettings {
+            process_names: vec![ProcessPattern::Regex("client_(".into())],
+            ..Default::default()
+        };
+
+        assert!(ProcessMatcher::new(&settings).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::{path::PathBuf, sync::{mpsc::{Receiver, Sender}, Arc, Mutex}, thread::JoinHandle, time::Duration};
+
+use chrono::Utc;
+use log::*;
+use tokio::runtime::Runtime;
+use anyhow::*;
+
+use crate::{aws_iprange::{AwsIpRange, FakeIpRanges}, models::{WatcherError, WatcherEvent}, stop_signal::StopSignal};
+
+pub use backend::{default_backend, NetworkProbe, ProcessBackend, ProcessBackendFactory, ProcessProbe, SocketPair};
+pub use matcher::ProcessMatcher;
+use tracker::ProcessTracker;
+
+mod backend;
+mod matcher;
+mod tracker;
+#[cfg(not(target_os = "linux"))]
+mod sysinfo_backend;
+#[cfg(target_os = "linux")]
+mod procfs_backend;
+#[cfg(target_os = "linux")]
+mod tcp_metrics;
+
+pub struct ProcessWatcher {
+    handle: Option<JoinHandle<Result<()>>>,
+    stop_signal: Arc<StopSignal>,
+    check_interval: Duration,
+    backend_factory: ProcessBackendFactory,
+    ip_ranges_file: Option<PathBuf>,
+    last_error: Arc<Mutex<Option<WatcherError>>>
+}
+
+impl ProcessWatcher {
+    /// Resolves server regions from the AWS ranges cached in `ip_ranges_file`,
+    /// without one only loopback servers are recognized.
+    pub fn new(check_interval: Duration, ip_ranges_file: Option<PathBuf>) -> Self {
+        Self {
+            ip_ranges_file,
+            ..Self::with_backend(check_interval, Arc::new(default_backend))
+        }
+    }
+
+    /// Creates a watcher whose probes are built by `backend_factory` on the watcher thread.
+    pub fn with_backend(check_interval: Duration, backend_factory: ProcessBackendFactory) -> Self {
+        Self {
+            handle: None,
+            stop_signal: Arc::new(StopSignal::new()),
+            check_interval,
+            backend_factory,
+            ip_ranges_file: None,
+            last_error: Arc::new(Mutex::new(None))
+        }
+    }
+
+    /// Takes effect on the next [`ProcessWatcher::start`].
+    pub fn set_check_interval(&mut self, check_interval: Duration) {
+        self.check_interval = check_interval;
+    }
+
+    /// Starts watching for the process, stopping the previous watcher thread if there is one.
+    pub fn start(&mut self, matcher: ProcessMatcher, port: u16) -> Receiver<WatcherEvent> {
+        if let Err(err) = self.stop() {
+            warn!("Previous process watcher exited with an error: {:?}", err);
+        }
+
+        // a stopped signal cannot be rearmed, every run gets its own
+        self.stop_signal = Arc::new(StopSignal::new());
+        *self.last_error.lock().unwrap() = None;
+
+        let (tx, rx) = std::sync::mpsc::channel::<WatcherEvent>();
+        let stop_signal = self.stop_signal.clone();
+        let check_interval = self.check_interval.clone();
+        let backend_factory = self.backend_factory.clone();
+        let ip_ranges_file = self.ip_ranges_file.clone();
+        let last_error = self.last_error.clone();
+        let handle = std::thread::spawn(move || {
+            let result = Self::check_periodically(
+                matcher,
+                port,
+                stop_signal,
+                tx,
+                check_interval,
+                backend_factory,
+                ip_ranges_file);
+
+            if let Err(err) = &result {
+                error!("Process watcher failed: {:?}", err);
+                *last_error.lock().unwrap() = Some(WatcherError {
+                    message: err.to_string(),
+                    occurred_on: Utc::now()
+                });
+            }
+
+            result
+        });
+
+        self.handle = Some(handle);
+
+        rx
+    }
+
+    fn check_periodically(
+        matcher: ProcessMatcher,
+        port: u16,
+        stop_signal: Arc<StopSignal>,
+        tx: Sender<WatcherEvent>,
+        check_interval: Duration,
+        backend_factory: ProcessBackendFactory,
+        ip_ranges_file: Option<PathBuf>
+    ) -> Result<()> {
+        let mut backend = backend_factory();
+        let rt = Runtime::new()?;
+        let ip_ranges = rt.block_on(async {
+            match ip_ranges_file {
+                Some(cached_json) => AwsIpRange::new(cached_json).get().await,
+                None => FakeIpRanges::new().get().await,
+            }
+        })?;
+        let mut tracker = ProcessTracker::new(matcher, port);
+
+        while !stop_signal.wait_timeout(check_interval) {
+            tracker.check(&mut backend, &ip_ranges.prefixes, &tx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the watcher thread has been started and has not exited yet.
+    pub fn is_thread_alive(&self) -> bool {
+        self.handle.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    pub fn last_error(&self) -> Option<WatcherError> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    /// Whether the watcher has been started and not stopped since.
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some() && !self.stop_signal.is_stopped()
+    }
+
+    /// Wakes up the watcher thread and waits for it to exit, the watcher can be started again afterwards.
+    pub fn stop(&mut self) -> Result<()> {
+        self.stop_signal.stop();
+        if let Some(handle) = self.handle.take() {
+            handle
+                .join()
+                .map_err(|err| anyhow::anyhow!("{:?}", err))??;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::{AtomicBool, Ordering}, time::Instant};
+
+    use crate::models::{ProcessInfo, ProcessPattern, SnifferSettings};
+
+    use super::*;
+
+    struct IdleProbe;
+
+    impl ProcessProbe for IdleProbe {
+        fn find_processes(&mut self, _matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+            vec![]
+        }
+
+        fn is_alive(&mut self, _process_id: u32) -> bool {
+            false
+        }
+    }
+
+    impl NetworkProbe for IdleProbe {
+        fn find_connections(&mut self, _process_id: u32, _port: u16) -> Result<Vec<SocketPair>> {
+            Ok(vec![])
+        }
+    }
+
+    /// Finds a client whose sockets can't be read, failing the first check.
+    struct FailingProbe;
+
+    impl ProcessProbe for FailingProbe {
+        fn find_processes(&mut self, _matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+            vec![ProcessInfo {
+                pid: 1234,
+                exe_path: None,
+                started_on: None,
+                first_seen_on: Utc::now()
+            }]
+        }
+
+        fn is_alive(&mut self, _process_id: u32) -> bool {
+            true
+        }
+    }
+
+    impl NetworkProbe for FailingProbe {
+        fn find_connections(&mut self, _process_id: u32, _port: u16) -> Result<Vec<SocketPair>> {
+            bail!("socket table unreadable")
+        }
+    }
+
+    fn idle_watcher(check_interval: Duration) -> ProcessWatcher {
+        ProcessWatcher::with_backend(check_interval, Arc::new(|| ProcessBackend::shared(IdleProbe)))
+    }
+
+    fn game_matcher() -> ProcessMatcher {
+        ProcessMatcher::new(&SnifferSettings {
+            process_names: vec![ProcessPattern::Exact("game.exe".into())],
+            ..Default::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn should_stop_without_waiting_for_check_interval() {
+        let mut watcher = idle_watcher(Duration::from_secs(60));
+        let _rx = watcher.start(game_matcher(), 6040);
+        assert!(watcher.is_running());
+
+        let stopped_on = Instant::now();
+        watcher.stop().unwrap();
+
+        assert!(stopped_on.elapsed() < Duration::from_secs(5));
+        assert!(!watcher.is_running());
+        assert!(!watcher.is_thread_alive());
+    }
+
+    #[test]
+    fn should_restart_after_stop() {
+        let mut watcher = idle_watcher(Duration::from_millis(10));
+        let _rx = watcher.start(game_matcher(), 6040);
+        watcher.stop().unwrap();
+
+        let rx = watcher.start(game_matcher(), 6040);
+        assert!(watcher.is_running());
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        watcher.stop().unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_err());
+    }
+
+    #[test]
+    fn should_start_again_after_failed_run() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let backend_factory: ProcessBackendFactory = {
+            let failing = failing.clone();
+            Arc::new(move || match failing.load(Ordering::Relaxed) {
+                true => ProcessBackend::shared(FailingProbe),
+                false => ProcessBackend::shared(IdleProbe),
+            })
+        };
+        let mut watcher = ProcessWatcher::with_backend(Duration::from_millis(10), backend_factory);
+
+        let rx = watcher.start(game_matcher(), 6040);
+        while rx.recv_timeout(Duration::from_secs(5)).is_ok() {}
+        // the channel closes before the thread has recorded its error
+        while watcher.is_thread_alive() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(watcher.last_error().is_some_and(|err| err.message.contains("socket table unreadable")));
+
+        // a restart stops the failed run first, which reports its error again
+        assert!(watcher.stop().is_err());
+
+        failing.store(false, Ordering::Relaxed);
+        let rx = watcher.start(game_matcher(), 6040);
+
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        assert!(watcher.is_running());
+        assert!(watcher.last_error().is_none());
+
+        watcher.stop().unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use std::{collections::{HashMap, HashSet}, fs, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, path::{Path, PathBuf}};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::*;
+
+use crate::models::{ConnectionSample, ProcessInfo};
+
+use super::{backend::{NetworkProbe, ProcessProbe, SocketPair}, tcp_metrics::{self, SocketSample}, ProcessMatcher};
+
+/// `comm` is truncated by the kernel to `TASK_COMM_LEN - 1` bytes.
+const COMM_MAX_LEN: usize = 15;
+
+/// `USER_HZ`, the unit of `starttime`, is 100 on every architecture we ship to.
+const CLOCK_TICKS_PER_SECOND: u64 = 100;
+
+/// `TCP_ESTABLISHED` in the `st` column of `/proc/net/tcp`.
+const TCP_ESTABLISHED: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcpEntry {
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote_addr: IpAddr,
+    pub remote_port: u16,
+    pub state: u8,
+    pub inode: u64,
+}
+
+/// Reads process and socket information straight from procfs.
+///
+/// Found processes are followed through their own `/proc/<pid>/stat`, with the start time
+/// guarding against pid reuse, so the full `/proc` scan only runs when the watcher asks for it.
+pub struct ProcfsBackend {
+    root: PathBuf,
+    tracked: HashMap<u32, u64>,
+    /// Established sockets by remote port, sampled at most once per check for every instance.
+    socket_samples: HashMap<u16, Vec<SocketSample>>
+}
+
+impl ProcfsBackend {
+    pub fn new() -> Self {
+        Self::with_root("/proc".into())
+    }
+
+    pub fn with_root(root: PathBuf) -> Self {
+        Self {
+            root,
+            tracked: HashMap::new(),
+            socket_samples: HashMap::new()
+        }
+    }
+
+    fn read_start_time(&self, process_id: u32) -> Option<u64> {
+        let stat = fs::read_to_string(self.root.join(process_id.to_string()).join("stat")).ok()?;
+        parse_start_time(&stat)
+    }
+
+    fn read_boot_time(&self) -> Option<u64> {
+        let stat = fs::read_to_string(self.root.join("stat")).ok()?;
+        parse_boot_time(&stat)
+    }
+
+    fn read_argv0(process_dir: &Path) -> Option<String> {
+        let cmdline = fs::read(process_dir.join("cmdline")).ok()?;
+        let argv0 = cmdline.split(|byte| *byte == 0).next()?;
+        Some(String::from_utf8_lossy(argv0).into_owned())
+    }
+
+    /// Wine and Proton report the preloader as `exe`, so prefer the path the client was launched with.
+    fn read_exe_path(process_dir: &Path) -> Option<PathBuf> {
+        match Self::read_argv0(process_dir) {
+            Some(argv0) if argv0.contains(['/', '\\']) => Some(argv0.into()),
+            _ => fs::read_link(process_dir.join("exe")).ok(),
+        }
+    }
+
+    fn matches(process_dir: &Path, matcher: &ProcessMatcher) -> bool {
+        if let Some(argv0) = Self::read_argv0(process_dir) {
+            let file_name = argv0.rsplit(['/', '\\']).next().unwrap_or_default();
+
+            if matcher.matches_name(file_name) {
+                return true;
+            }
+        }
+
+        let matches_comm = match fs::read_to_string(process_dir.join("comm")) {
+            Ok(comm) => {
+                let comm = comm.trim_end_matches('\n');
+                matcher.matches_name(comm) || matcher.matches_truncated_name(comm, COMM_MAX_LEN)
+            },
+            Err(_) => false,
+        };
+
+        // resolving the path costs a readlink per process, only do it when the settings ask for it
+        matches_comm
+            || matcher.wants_path() && Self::read_exe_path(process_dir).is_some_and(|path| matcher.matches_path(&path))
+    }
+
+    fn socket_inodes(&self, process_id: u32) -> Result<HashSet<u64>> {
+        let fd_dir = self.root.join(process_id.to_string()).join("fd");
+        let mut inodes = HashSet::new();
+
+        for entry in fs::read_dir(fd_dir)? {
+            let Ok(entry) = entry else {
+                continue;
+            };
+
+            let Ok(target) = fs::read_link(entry.path()) else {
+                continue;
+            };
+
+            if let Some(inode) = parse_socket_inode(&target.to_string_lossy()) {
+                inodes.insert(inode);
+            }
+        }
+
+        Ok(inodes)
+    }
+}
+
+impl ProcessProbe for ProcfsBackend {
+    fn find_processes(&mut self, matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Could not read {:?}: {}", self.root, err);
+                return vec![];
+            },
+        };
+
+        let mut process_ids: Vec<u32> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+            .filter(|process_id: &u32| Self::matches(&self.root.join(process_id.to_string()), matcher))
+            .collect();
+        process_ids.sort_unstable();
+
+        let boot_time = self.read_boot_time();
+
+        process_ids.into_iter()
+            .map(|process_id| {
+                let start_time = self.read_start_time(process_id);
+
+                if let Some(start_time) = start_time {
+                    self.tracked.insert(process_id, start_time);
+                }
+
+                let started_on = start_time
+                    .zip(boot_time)
+                    .and_then(|(start_time, boot_time)| {
+                        let seconds = boot_time + start_time / CLOCK_TICKS_PER_SECOND;
+                        DateTime::from_timestamp(seconds as i64, 0)
+                    });
+
+                ProcessInfo {
+                    pid: process_id,
+                    exe_path: Self::read_exe_path(&self.root.join(process_id.to_string())),
+                    started_on,
+                    first_seen_on: Utc::now()
+                }
+            })
+            .collect()
+    }
+
+    fn is_alive(&mut self, process_id: u32) -> bool {
+        let is_alive = match (self.tracked.get(&process_id), self.read_start_time(process_id)) {
+            (Some(tracked_start_time), Some(start_time)) => *tracked_start_time == start_time,
+            (None, start_time) => start_time.is_some(),
+            (Some(_), None) => false,
+        };
+
+        if !is_alive {
+            self.tracked.remove(&process_id);
+        }
+
+        is_alive
+    }
+}
+
+impl NetworkProbe for ProcfsBackend {
+    fn refresh(&mut self) {
+        self.socket_samples.clear();
+    }
+
+    fn find_connections(&mut self, process_id: u32, port: u16) -> Result<Vec<SocketPair>> {
+        // the process may exit between checks, treat an unreadable fd table as no sockets
+        let inodes = match self.socket_inodes(process_id) {
+            Ok(inodes) => inodes,
+            Err(err) => {
+                debug!("Could not read sockets of {}: {}", process_id, err);
+                return Ok(vec![]);
+            },
+        };
+
+        if inodes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut sockets = vec![];
+
+        for table in ["tcp", "tcp6"] {
+            let Ok(contents) = fs::read_to_string(self.root.join("net").join(table)) else {
+                continue;
+            };
+
+            sockets.extend(parse_tcp_table(&contents)
+                .into_iter()
+                // sockets still closing or waiting to be reaped must not keep a client connected
+                .filter(|entry| entry.state == TCP_ESTABLISHED)
+                .filter(|entry| inodes.contains(&entry.inode) && entry.remote_port == port)
+                .map(|entry| SocketPair {
+                    local: SocketAddr::new(entry.local_addr, entry.local_port),
+                    remote: SocketAddr::new(entry.remote_addr, entry.remote_port)
+                }));
+        }
+
+        Ok(sockets)
+    }
+
+    fn sample_connection(&mut self, socket: &SocketPair) -> Option<ConnectionSample> {
+        let samples = self.socket_samples
+            .entry(socket.remote.port())
+            .or_insert_with(|| tcp_metrics::sample_established(socket.remote.port()));
+
+        samples.iter()
+            .find(|sample| sample.local.port() == socket.local.port() && sample.remote == socket.remote)
+            .map(|sample| sample.sample.clone())
+    }
+}
+
+/// Extracts `starttime` (field 22) from the contents of `/proc/<pid>/stat`.
+pub fn parse_start_time(stat: &str) -> Option<u64> {
+    // comm may contain spaces and parentheses, the remaining fields start after the last ')'
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Extracts `btime`, the boot time in seconds since the epoch, from the contents of `/proc/stat`.
+pub fn parse_boot_time(stat: &str) -> Option<u64> {
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Parses a `socket:[12345]` fd link target.
+pub fn parse_socket_inode(target: &str) -> Option<u64> {
+    target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// Parses the contents of `/proc/net/tcp` or `/proc/net/tcp6`, skipping malformed lines.
+pub fn parse_tcp_table(contents: &str) -> Vec<TcpEntry> {
+    contents.lines()
+        .skip(1)
+        .filter_map(parse_tcp_line)
+        .collect()
+}
+
+fn parse_tcp_line(line: &str) -> Option<TcpEntry> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    let (local_addr, local_port) = parse_endpoint(fields.get(1)?)?;
+    let (remote_addr, remote_port) = parse_endpoint(fields.get(2)?)?;
+    let state = u8::from_str_radix(fields.get(3)?, 16).ok()?;
+    let inode = fields.get(9)?.parse().ok()?;
+
+    Some(TcpEntry {
+        local_addr,
+        local_port,
+        remote_addr,
+        remote_port,
+        state,
+        inode
+    })
+}
+
+fn parse_endpoint(endpoint: &str) -> Option<(IpAddr, u16)> {
+    let (addr, port) = endpoint.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    // addresses are dumped as native-endian 32-bit words of the network-order address
+    let addr = match addr.len() {
+        8 => {
+            let word = u32::from_str_radix(addr, 16).ok()?;
+            IpAddr::V4(Ipv4Addr::from(word.to_ne_bytes()))
+        },
+        32 => {
+            let mut octets = [0u8; 16];
+            for (index, chunk) in octets.chunks_mut(4).enumerate() {
+                let word = u32::from_str_radix(&addr[index * 8..index * 8 + 8], 16).ok()?;
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+
+            let addr = Ipv6Addr::from(octets);
+            match addr.to_ipv4_mapped() {
+                Some(addr) => IpAddr::V4(addr),
+                None => IpAddr::V6(addr),
+            }
+        },
+        _ => return None,
+    };
+
+    Some((addr, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{ProcessPattern, SnifferSettings};
+
+    use super::*;
+
+    fn fixture_root() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("proc")
+    }
+
+    #[test]
+    fn should_parse_tcp_table() {
+        let contents = fs::read_to_string(fixture_root().join("net").join("tcp")).unwrap();
+
+        let entries = parse_tcp_table(&contents);
+
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[1].local_addr, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)));
+        assert_eq!(entries[1].remote_addr, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+        assert_eq!(entries[1].remote_port, 6040);
+        assert_eq!(entries[1].state, 1);
+        assert_eq!(entries[1].inode, 4242);
+    }
+
+    #[test]
+    fn should_map_ipv4_in_tcp6_table() {
+        let contents = fs::read_to_string(fixture_root().join("net").join("tcp6")).unwrap();
+
+        let entries = parse_tcp_table(&contents);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].remote_addr, IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        assert_eq!(entries[1].remote_addr, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)));
+        assert_eq!(entries[1].remote_port, 6040);
+    }
+
+    #[test]
+    fn should_parse_start_time() {
+        let stat = fs::read_to_string(fixture_root().join("1234").join("stat")).unwrap();
+
+        assert_eq!(parse_start_time(&stat), Some(987654));
+    }
+
+    fn matcher(process_names: Vec<ProcessPattern>, match_executable_path: bool) -> ProcessMatcher {
+        ProcessMatcher::new(&SnifferSettings {
+            process_names,
+            match_executable_path,
+            ..Default::default()
+        }).unwrap()
+    }
+
+    fn exact(name: &str) -> ProcessMatcher {
+        matcher(vec![ProcessPattern::Exact(name.into())], false)
+    }
+
+    #[test]
+    fn should_find_process_by_name() {
+        let mut backend = ProcfsBackend::with_root(fixture_root());
+
+        let processes = backend.find_processes(&exact("client_server.exe"));
+        let process = processes[0].clone();
+
+        assert_eq!(processes.len(), 1);
+        assert_eq!(process.pid, 1234);
+        assert_eq!(process.started_on, DateTime::from_timestamp(1_700_000_000 + 9876, 0));
+        assert!(process.exe_path.unwrap().to_string_lossy().ends_with("Win64\\client_server.exe"));
+        assert!(backend.find_processes(&exact("missing.exe")).is_empty());
+        assert!(backend.is_alive(1234));
+        assert!(!backend.is_alive(4321));
+    }
+
+    #[test]
+    fn should_find_process_by_executable_path() {
+        let mut backend = ProcfsBackend::with_root(fixture_root());
+        let patterns = vec![ProcessPattern::Glob("*/lost ark/binaries/*/*.exe".into())];
+
+        assert_eq!(backend.find_processes(&matcher(patterns.clone(), true)).len(), 1);
+        assert!(backend.find_processes(&matcher(patterns, false)).is_empty());
+        assert_eq!(backend.find_processes(&exact("CLIENT_SERVER.EXE")).len(), 1);
+    }
+
+    #[test]
+    fn should_find_connections_of_process_sockets() {
+        let mut backend = ProcfsBackend::with_root(fixture_root());
+
+        let ip_addrs: Vec<_> = backend.find_connections(1234, 6040).unwrap()
+            .into_iter()
+            .map(|socket| socket.remote.ip())
+            .collect();
+
+        assert_eq!(ip_addrs, vec![
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))
+        ]);
+    }
+
+    #[test]
+    fn should_skip_sockets_that_are_not_established() {
+        let contents = fs::read_to_string(fixture_root().join("net").join("tcp")).unwrap();
+        let mut backend = ProcfsBackend::with_root(fixture_root());
+
+        let states: Vec<_> = parse_tcp_table(&contents)
+            .into_iter()
+            .filter(|entry| entry.remote_port == 6040)
+            .map(|entry| entry.state)
+            .collect();
+
+        let remote_ips: Vec<_> = backend.find_connections(1234, 6040).unwrap()
+            .into_iter()
+            .map(|socket| socket.remote.ip())
+            .collect();
+
+        // CLOSE_WAIT and TIME_WAIT sockets of the client are in the table but don't count
+        assert_eq!(states, vec![0x01, 0x08, 0x06]);
+        assert!(!remote_ips.contains(&IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3))));
+        assert!(!remote_ips.contains(&IpAddr::V4(Ipv4Addr::new(127, 0, 0, 4))));
+    }
+}
@@ -0,0 +1,81 @@
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use netstat::*;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::models::ProcessInfo;
+
+use super::{backend::{NetworkProbe, ProcessProbe, SocketPair}, ProcessMatcher};
+
+pub struct SysinfoBackend {
+    system: System
+}
+
+impl SysinfoBackend {
+    pub fn new() -> Self {
+        Self {
+            system: System::new_all()
+        }
+    }
+}
+
+impl ProcessProbe for SysinfoBackend {
+    fn find_processes(&mut self, matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+        self.system.refresh_processes_specifics(ProcessesToUpdate::All, false, ProcessRefreshKind::everything());
+        let mut processes: Vec<_> = self.system.processes()
+            .values()
+            .filter(|p| matcher.matches_name(&p.name().to_string_lossy())
+                || p.exe().is_some_and(|path| matcher.matches_path(path)))
+            .map(|p| ProcessInfo {
+                pid: p.pid().as_u32(),
+                exe_path: p.exe().map(|path| path.to_path_buf()),
+                started_on: DateTime::from_timestamp(p.start_time() as i64, 0),
+                first_seen_on: Utc::now()
+            })
+            .collect();
+        processes.sort_by_key(|process| process.pid);
+
+        processes
+    }
+
+    fn is_alive(&mut self, process_id: u32) -> bool {
+        let size = self.system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[Pid::from_u32(process_id)]),
+            true,
+            ProcessRefreshKind::nothing());
+
+        size != 0
+    }
+}
+
+/// Network probe backed by `netstat`, it keeps no state between checks.
+pub struct NetstatProbe;
+
+impl NetworkProbe for NetstatProbe {
+    fn find_connections(&mut self, process_id: u32, port: u16) -> Result<Vec<SocketPair>> {
+        let address_family_flags = AddressFamilyFlags::IPV4;
+        let proto = ProtocolFlags::TCP;
+
+        let sockets = get_sockets_info(address_family_flags, proto)
+            .ok()
+            .unwrap_or_default();
+
+        let sockets = sockets.into_iter()
+            .filter(|socket| socket.associated_pids.contains(&process_id))
+            .filter_map(|info| {
+                if let ProtocolSocketInfo::Tcp(tcp) = info.protocol_socket_info {
+                    (tcp.remote_port == port && tcp.state == TcpState::Established).then(|| SocketPair {
+                        local: SocketAddr::new(tcp.local_addr, tcp.local_port),
+                        remote: SocketAddr::new(tcp.remote_addr, tcp.remote_port)
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Ok(sockets)
+    }
+}
@@ -0,0 +1,173 @@
+use std::{io::ErrorKind, net::{IpAddr, SocketAddr}, process::Command, sync::atomic::{AtomicBool, Ordering}};
+
+use chrono::Utc;
+use log::*;
+
+use crate::models::ConnectionSample;
+
+/// TCP metrics of a single established socket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketSample {
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+    pub sample: ConnectionSample
+}
+
+/// Whether the missing `ss` binary has been reported, so the warning shows once per run of the app.
+static SS_MISSING_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Samples RTT and retransmits of every established connection to `port` through a single `ss` run,
+/// `ss` reads `tcp_info` over sock_diag and therefore works for sockets owned by the game client.
+///
+/// `ss` ships with iproute2, without it connection quality is unavailable and no samples are returned.
+pub fn sample_established(port: u16) -> Vec<SocketSample> {
+    let filter = format!("dport = :{}", port);
+
+    let output = Command::new("ss")
+        .args(["-t", "-i", "-n", "-H", "state", "established", &filter])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!("ss exited with {}", output.status);
+            return vec![];
+        },
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            if !SS_MISSING_REPORTED.swap(true, Ordering::Relaxed) {
+                warn!("ss (iproute2) is not installed, connection quality is unavailable");
+            }
+            return vec![];
+        },
+        Err(err) => {
+            debug!("Could not run ss: {}", err);
+            return vec![];
+        },
+    };
+
+    parse_ss_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses `ss -tinH` output, every socket line is followed by an indented line of metrics.
+///
+/// `ss` omits `retrans` while nothing has been retransmitted.
+pub fn parse_ss_output(output: &str) -> Vec<SocketSample> {
+    let mut samples = vec![];
+    let mut endpoints = None;
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            endpoints = parse_socket_line(line);
+            continue;
+        }
+
+        if let Some((local, remote)) = endpoints.take() {
+            if let Some(sample) = parse_info_line(line) {
+                samples.push(SocketSample { local, remote, sample });
+            }
+        }
+    }
+
+    samples
+}
+
+/// `Recv-Q Send-Q Local Peer`, the state column is left out when filtering by state.
+fn parse_socket_line(line: &str) -> Option<(SocketAddr, SocketAddr)> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    let local = parse_endpoint(fields.get(2)?)?;
+    let remote = parse_endpoint(fields.get(3)?)?;
+
+    Some((local, remote))
+}
+
+/// Parses `1.2.3.4:6040` or `[::ffff:1.2.3.4%eth0]:6040`, IPv4-mapped addresses become IPv4
+/// to compare equal to the addresses read from procfs.
+fn parse_endpoint(endpoint: &str) -> Option<SocketAddr> {
+    let (addr, port) = endpoint.rsplit_once(':')?;
+    let addr = addr.trim_start_matches('[').trim_end_matches(']');
+    let addr = addr.split_once('%').map_or(addr, |(addr, _)| addr);
+
+    let addr = match addr.parse().ok()? {
+        IpAddr::V6(addr) => addr.to_ipv4_mapped().map_or(IpAddr::V6(addr), IpAddr::V4),
+        addr => addr,
+    };
+
+    Some(SocketAddr::new(addr, port.parse().ok()?))
+}
+
+fn parse_info_line(line: &str) -> Option<ConnectionSample> {
+    let mut rtt = None;
+    let mut retransmits = 0;
+
+    for token in line.split_whitespace() {
+        if let Some(value) = token.strip_prefix("rtt:") {
+            let (rtt_ms, rtt_var_ms) = value.split_once('/')?;
+            rtt = Some((rtt_ms.parse().ok()?, rtt_var_ms.parse().ok()?));
+        } else if let Some(value) = token.strip_prefix("retrans:") {
+            let (_, total) = value.split_once('/')?;
+            retransmits = total.parse().ok()?;
+        }
+    }
+
+    let (rtt_ms, rtt_var_ms) = rtt?;
+
+    Some(ConnectionSample {
+        sampled_on: Utc::now(),
+        rtt_ms,
+        rtt_var_ms,
+        retransmits
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("ss").join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn should_parse_rtt_and_retransmits() {
+        let samples = parse_ss_output(&fixture("established.txt"));
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].local, "192.168.1.20:50000".parse().unwrap());
+        assert_eq!(samples[0].remote, "18.196.42.7:6040".parse().unwrap());
+        assert_eq!(samples[0].sample.rtt_ms, 38.512);
+        assert_eq!(samples[0].sample.rtt_var_ms, 4.25);
+        assert_eq!(samples[0].sample.retransmits, 7);
+    }
+
+    #[test]
+    fn should_default_retransmits_to_zero() {
+        let samples = parse_ss_output(&fixture("no_retrans.txt"));
+
+        assert_eq!(samples[0].sample.rtt_ms, 12.0);
+        assert_eq!(samples[0].sample.retransmits, 0);
+    }
+
+    #[test]
+    fn should_keep_sockets_to_same_server_apart() {
+        let samples = parse_ss_output(&fixture("two_clients.txt"));
+        let local_ports: Vec<_> = samples.iter().map(|sample| sample.local.port()).collect();
+
+        assert_eq!(local_ports, vec![50000, 50001]);
+        assert_eq!(samples[1].remote, "18.196.42.7:6040".parse().unwrap());
+        assert_eq!(samples[1].sample.rtt_ms, 12.0);
+    }
+
+    #[test]
+    fn should_map_ipv4_in_ipv6_endpoints() {
+        assert_eq!(parse_endpoint("[::ffff:18.196.42.7]:6040"), "18.196.42.7:6040".parse().ok());
+        assert_eq!(parse_endpoint("[fe80::1%eth0]:6040"), "[fe80::1]:6040".parse().ok());
+    }
+
+    #[test]
+    fn should_ignore_empty_output() {
+        assert!(parse_ss_output("").is_empty());
+    }
+}
@@ -0,0 +1,589 @@
+use std::{net::IpAddr, sync::mpsc::Sender};
+
+use anyhow::*;
+use chrono::{DateTime, Utc};
+use ipnetwork::IpNetwork;
+use uuid::Uuid;
+
+use crate::{aws_iprange::IpPrefix, models::{ConnectionInfo, ProcessEvent, ProcessInfo, ProcessState, ReconnectInfo, StoppedInfo, WatcherEvent}};
+
+use super::{backend::{NetworkProbe, ProcessBackend, SocketPair}, ProcessMatcher};
+
+/// Number of checks between scans for additional clients while at least one is tracked.
+const RESCAN_EVERY: u32 = 5;
+
+/// State machine behind the [`ProcessWatcher`](super::ProcessWatcher), advanced once per check.
+///
+/// Every matching process becomes an instance with its own id and state; `ProcessNotRunning`
+/// is only reported when no client is found on startup.
+pub struct ProcessTracker {
+    matcher: ProcessMatcher,
+    port: u16,
+    instances: Vec<InstanceTracker>,
+    has_reported: bool,
+    checks_since_scan: u32
+}
+
+struct InstanceTracker {
+    instance_id: Uuid,
+    process: ProcessInfo,
+    last_message: ProcessState,
+    connection: Option<ConnectionInfo>,
+    last_connection: Option<ConnectionInfo>
+}
+
+impl ProcessTracker {
+    pub fn new(matcher: ProcessMatcher, port: u16) -> Self {
+        Self {
+            matcher,
+            port,
+            instances: vec![],
+            has_reported: false,
+            checks_since_scan: 0
+        }
+    }
+
+    pub fn check(&mut self, backend: &mut ProcessBackend, prefixes: &[IpPrefix], tx: &Sender<WatcherEvent>) -> Result<()> {
+        for mut instance in std::mem::take(&mut self.instances) {
+            if backend.process_probe.is_alive(instance.process.pid) {
+                self.instances.push(instance);
+            } else {
+                instance.handle_process_stopped(tx)?;
+            }
+        }
+
+        self.checks_since_scan += 1;
+
+        if self.instances.is_empty() || self.checks_since_scan >= RESCAN_EVERY {
+            self.checks_since_scan = 0;
+
+            for process in backend.process_probe.find_processes(&self.matcher) {
+                if self.instances.iter().all(|instance| instance.process.pid != process.pid) {
+                    self.instances.push(InstanceTracker::new(process));
+                }
+            }
+        }
+
+        if self.instances.is_empty() {
+            if !self.has_reported {
+                self.has_reported = true;
+                tx.send(WatcherEvent::State(ProcessEvent {
+                    instance_id: None,
+                    state: ProcessState::ProcessNotRunning
+                }))?;
+            }
+
+            return Ok(());
+        }
+
+        self.has_reported = true;
+        backend.network_probe.refresh();
+
+        for instance in &mut self.instances {
+            instance.check(backend.network_probe.as_mut(), prefixes, self.port, tx)?;
+        }
+
+        Ok(())
+    }
+
+    fn match_ip(prefixes: &[IpPrefix], ip_addr: &IpAddr) -> Result<Option<String>> {
+        for prefix in prefixes {
+            let network: IpNetwork = prefix.ip_prefix.parse()?;
+            if network.contains(*ip_addr) {
+                return Ok(Some(prefix.region.clone()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl InstanceTracker {
+    fn new(process: ProcessInfo) -> Self {
+        Self {
+            instance_id: Uuid::now_v7(),
+            process,
+            last_message: ProcessState::Unknown,
+            connection: None,
+            last_connection: None
+        }
+    }
+
+    fn check(&mut self, network_probe: &mut dyn NetworkProbe, prefixes: &[IpPrefix], port: u16, tx: &Sender<WatcherEvent>) -> Result<()> {
+        let process = self.process.clone();
+
+        self.send_message(tx, ProcessState::ProcessRunning(process.clone()))?;
+
+        let sockets = network_probe.find_connections(process.pid, port)?;
+        let mut candidates = vec![];
+
+        for socket in sockets {
+            if let Some(region) = ProcessTracker::match_ip(prefixes, &socket.remote.ip())? {
+                candidates.push((socket, region));
+            }
+        }
+
+        if candidates.is_empty() {
+            self.connection = None;
+            return self.send_message(tx, ProcessState::ProcessNotListening(process));
+        }
+
+        // while the old and new sockets overlap during a transfer, stay on the current one
+        let current_ip = self.connection.as_ref().map(|connection| connection.remote_ip);
+        let selected = candidates.iter()
+            .position(|(socket, _)| Some(socket.remote.ip()) == current_ip)
+            .unwrap_or(0);
+        let (socket, region) = candidates.swap_remove(selected);
+        let remote_ip = socket.remote.ip();
+
+        let connection = match self.connection.take() {
+            Some(connection) if connection.remote_ip == remote_ip => connection,
+            _ => ConnectionInfo {
+                process,
+                remote_ip,
+                remote_port: port,
+                region,
+                connected_on: Utc::now()
+            },
+        };
+        self.connection = Some(connection.clone());
+
+        match self.last_connection.replace(connection.clone()) {
+            Some(previous) if previous.remote_ip != connection.remote_ip => {
+                self.send_reconnected(tx, previous, connection.clone())?;
+            },
+            _ => self.send_message(tx, ProcessState::ProcessListening(connection.clone()))?,
+        }
+
+        // clients on the same server share the remote endpoint, the local one tells them apart
+        if let Some(sample) = network_probe.sample_connection(&socket) {
+            tx.send(WatcherEvent::ConnectionSample {
+                instance_id: self.instance_id,
+                sample
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the endpoint change once, afterwards the instance is simply listening on the new endpoint.
+    fn send_reconnected(&mut self, tx: &Sender<WatcherEvent>, previous: ConnectionInfo, connection: ConnectionInfo) -> Result<()> {
+        tx.send(WatcherEvent::State(ProcessEvent {
+            instance_id: Some(self.instance_id),
+            state: ProcessState::ProcessReconnected(ReconnectInfo {
+                previous,
+                connection: connection.clone()
+            })
+        }))?;
+        self.last_message = ProcessState::ProcessListening(connection);
+
+        Ok(())
+    }
+
+    fn send_message(&mut self, tx: &Sender<WatcherEvent>, new_message: ProcessState) -> Result<()> {
+
+        // a live process is already implied by the listening states
+        let should_skip = matches!(new_message, ProcessState::ProcessRunning(_))
+            && matches!(self.last_message, ProcessState::ProcessNotListening(_) | ProcessState::ProcessListening(_));
+
+        if should_skip {
+            return Ok(());
+        }
+
+        if self.last_message != new_message {
+            tx.send(WatcherEvent::State(ProcessEvent {
+                instance_id: Some(self.instance_id),
+                state: new_message.clone()
+            }))?;
+            self.last_message = new_message;
+        }
+        Ok(())
+    }
+
+    fn handle_process_stopped(&mut self, tx: &Sender<WatcherEvent>) -> Result<()> {
+        let new_message = ProcessState::ProcessStopped(StoppedInfo {
+            process: self.process.clone(),
+            stopped_on: Utc::now()
+        });
+
+        self.send_message(tx, new_message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::SocketAddr, sync::{mpsc::channel, Arc, Mutex}};
+
+    use crate::{models::{ConnectionSample, ProcessPattern, SnifferSettings}, process_watcher::backend::{NetworkProbe, ProcessProbe}};
+
+    use super::*;
+
+    /// What the probes report during one check, the remote addresses of each running process
+    /// and the RTT of its connections.
+    #[derive(Clone, Default)]
+    struct Step {
+        processes: Vec<(u32, Vec<IpAddr>)>,
+        rtt_ms: HashMap<u32, f64>
+    }
+
+    impl Step {
+        fn not_running() -> Self {
+            Self::default()
+        }
+
+        fn running(process_id: u32, remote_addrs: &[&str]) -> Self {
+            Self::default().and(process_id, remote_addrs)
+        }
+
+        fn and(mut self, process_id: u32, remote_addrs: &[&str]) -> Self {
+            let remote_addrs = remote_addrs.iter().map(|addr| addr.parse().unwrap()).collect();
+            self.processes.push((process_id, remote_addrs));
+            self
+        }
+
+        fn with_rtt(mut self, process_id: u32, rtt_ms: f64) -> Self {
+            self.rtt_ms.insert(process_id, rtt_ms);
+            self
+        }
+    }
+
+    /// Every scripted process gets its own local port.
+    fn local_port(process_id: u32) -> u16 {
+        50000 + (process_id % 10000) as u16
+    }
+
+    struct ScriptedProbe {
+        step: Arc<Mutex<Step>>
+    }
+
+    impl ProcessProbe for ScriptedProbe {
+        fn find_processes(&mut self, _matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+            let step = self.step.lock().unwrap();
+            step.processes.iter().map(|(pid, _)| ProcessInfo {
+                pid: *pid,
+                exe_path: None,
+                started_on: None,
+                first_seen_on: Utc::now()
+            })
+            .collect()
+        }
+
+        fn is_alive(&mut self, process_id: u32) -> bool {
+            let step = self.step.lock().unwrap();
+            step.processes.iter().any(|(pid, _)| *pid == process_id)
+        }
+    }
+
+    impl NetworkProbe for ScriptedProbe {
+        fn find_connections(&mut self, process_id: u32, port: u16) -> Result<Vec<SocketPair>> {
+            let step = self.step.lock().unwrap();
+            let (_, remote_addrs) = step.processes.iter()
+                .find(|(pid, _)| *pid == process_id)
+                .expect("probed a process that is not running");
+            Ok(remote_addrs.iter()
+                .map(|remote_addr| SocketPair {
+                    local: SocketAddr::new("192.168.1.20".parse().unwrap(), local_port(process_id)),
+                    remote: SocketAddr::new(*remote_addr, port)
+                })
+                .collect())
+        }
+
+        fn sample_connection(&mut self, socket: &SocketPair) -> Option<ConnectionSample> {
+            let step = self.step.lock().unwrap();
+            let (process_id, _) = step.processes.iter().find(|(pid, _)| local_port(*pid) == socket.local.port())?;
+            step.rtt_ms.get(process_id).map(|rtt_ms| ConnectionSample {
+                sampled_on: Utc::now(),
+                rtt_ms: *rtt_ms,
+                rtt_var_ms: 0.0,
+                retransmits: 0
+            })
+        }
+    }
+
+    fn summarize(state: &ProcessState) -> String {
+        match state {
+            ProcessState::Unknown => "Unknown".into(),
+            ProcessState::ProcessNotRunning => "NotRunning".into(),
+            ProcessState::ProcessRunning(process) => format!("Running({})", process.pid),
+            ProcessState::ProcessNotListening(process) => format!("NotListening({})", process.pid),
+            ProcessState::ProcessListening(connection) => format!(
+                "Listening({}, {}:{}, {})",
+                connection.process.pid,
+                connection.remote_ip,
+                connection.remote_port,
+                connection.region),
+            ProcessState::ProcessReconnected(reconnect) => format!(
+                "Reconnected({}, {} -> {}, {})",
+                reconnect.connection.process.pid,
+                reconnect.previous.remote_ip,
+                reconnect.connection.remote_ip,
+                reconnect.connection.region),
+            ProcessState::ProcessStopped(stopped) => format!("Stopped({})", stopped.process.pid),
+        }
+    }
+
+    fn run_watcher(steps: &[Step]) -> Vec<WatcherEvent> {
+        let current = Arc::new(Mutex::new(Step::default()));
+        let mut backend = ProcessBackend {
+            process_probe: Box::new(ScriptedProbe { step: current.clone() }),
+            network_probe: Box::new(ScriptedProbe { step: current.clone() })
+        };
+        let prefixes = vec![IpPrefix {
+            ip_prefix: "127.0.0.0/8".into(),
+            region: "EUC".into(),
+            service: "n/a".into(),
+            network_border_group: "n/a".into()
+        }];
+        let (tx, rx) = channel();
+        let matcher = ProcessMatcher::new(&SnifferSettings {
+            process_names: vec![ProcessPattern::Exact("client_server.exe".into())],
+            ..Default::default()
+        }).unwrap();
+        let mut tracker = ProcessTracker::new(matcher, 6040);
+
+        for step in steps {
+            *current.lock().unwrap() = step.clone();
+            tracker.check(&mut backend, &prefixes, &tx).unwrap();
+        }
+
+        drop(tx);
+        rx.into_iter().collect()
+    }
+
+    fn run_timeline(steps: &[Step]) -> Vec<ProcessEvent> {
+        run_watcher(steps).into_iter()
+            .filter_map(|event| match event {
+                WatcherEvent::State(event) => Some(event),
+                WatcherEvent::ConnectionSample { .. } => None,
+            })
+            .collect()
+    }
+
+    fn run_summarized(steps: &[Step]) -> Vec<String> {
+        run_timeline(steps).iter().map(|event| summarize(&event.state)).collect()
+    }
+
+    /// Summarizes events as `#<n> <state>`, numbering instances in order of appearance.
+    fn run_by_instance(steps: &[Step]) -> Vec<String> {
+        let mut instances = HashMap::new();
+
+        run_timeline(steps).iter()
+            .map(|event| {
+                let next = instances.len() + 1;
+                let number = event.instance_id
+                    .map(|instance_id| *instances.entry(instance_id).or_insert(next));
+
+                match number {
+                    Some(number) => format!("#{} {}", number, summarize(&event.state)),
+                    None => summarize(&event.state),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_report_not_running_once() {
+        let states = run_summarized(&[
+            Step::not_running(),
+            Step::not_running(),
+            Step::not_running()
+        ]);
+
+        assert_eq!(states, vec!["NotRunning"]);
+    }
+
+    #[test]
+    fn should_follow_process_lifecycle() {
+        let states = run_summarized(&[
+            Step::not_running(),
+            Step::running(1234, &[]),
+            Step::running(1234, &["127.0.0.1"]),
+            Step::running(1234, &["127.0.0.1"]),
+            Step::not_running(),
+            Step::not_running()
+        ]);
+
+        assert_eq!(states, vec![
+            "NotRunning",
+            "Running(1234)",
+            "NotListening(1234)",
+            "Listening(1234, 127.0.0.1:6040, EUC)",
+            "Stopped(1234)"
+        ]);
+    }
+
+    #[test]
+    fn should_skip_running_while_listening_state_is_known() {
+        let states = run_summarized(&[
+            Step::running(1234, &["127.0.0.1"]),
+            Step::running(1234, &[]),
+            Step::running(1234, &[]),
+            Step::running(1234, &["127.0.0.1"])
+        ]);
+
+        assert_eq!(states, vec![
+            "Running(1234)",
+            "Listening(1234, 127.0.0.1:6040, EUC)",
+            "NotListening(1234)",
+            "Listening(1234, 127.0.0.1:6040, EUC)"
+        ]);
+    }
+
+    #[test]
+    fn should_report_not_listening_outside_known_ranges() {
+        let states = run_summarized(&[
+            Step::running(1234, &["10.0.0.1"])
+        ]);
+
+        assert_eq!(states, vec!["Running(1234)", "NotListening(1234)"]);
+    }
+
+    #[test]
+    fn should_stop_before_tracking_replaced_process() {
+        let states = run_by_instance(&[
+            Step::running(1234, &[]),
+            Step::running(5678, &[]),
+            Step::running(5678, &[])
+        ]);
+
+        assert_eq!(states, vec![
+            "#1 Running(1234)",
+            "#1 NotListening(1234)",
+            "#1 Stopped(1234)",
+            "#2 Running(5678)",
+            "#2 NotListening(5678)"
+        ]);
+    }
+
+    #[test]
+    fn should_track_clients_independently() {
+        let states = run_by_instance(&[
+            Step::running(1234, &["127.0.0.1"]).and(5678, &[]),
+            Step::running(1234, &["127.0.0.1"]).and(5678, &["127.0.0.2"]),
+            Step::running(5678, &["127.0.0.2"])
+        ]);
+
+        assert_eq!(states, vec![
+            "#1 Running(1234)",
+            "#1 Listening(1234, 127.0.0.1:6040, EUC)",
+            "#2 Running(5678)",
+            "#2 NotListening(5678)",
+            "#2 Listening(5678, 127.0.0.2:6040, EUC)",
+            "#1 Stopped(1234)"
+        ]);
+    }
+
+    #[test]
+    fn should_pick_up_additional_client_on_rescan() {
+        let mut steps = vec![Step::running(1234, &[])];
+        steps.extend((1..=RESCAN_EVERY).map(|_| Step::running(1234, &[]).and(5678, &[])));
+
+        let states = run_by_instance(&steps);
+
+        assert_eq!(states, vec![
+            "#1 Running(1234)",
+            "#1 NotListening(1234)",
+            "#2 Running(5678)",
+            "#2 NotListening(5678)"
+        ]);
+    }
+
+    #[test]
+    fn should_keep_connection_timestamp_while_endpoint_is_unchanged() {
+        let states = run_timeline(&[
+            Step::running(1234, &["127.0.0.1"]),
+            Step::running(1234, &["127.0.0.1"]),
+            Step::not_running()
+        ]);
+
+        let ProcessState::ProcessListening(connection) = &states[1].state else {
+            panic!("expected listening state, got {:?}", states[1]);
+        };
+        let ProcessState::ProcessStopped(stopped) = &states[2].state else {
+            panic!("expected stopped state, got {:?}", states[2]);
+        };
+
+        assert_eq!(states.len(), 3);
+        assert_eq!(stopped.process, connection.process);
+        assert!(stopped.stopped_on >= connection.connected_on);
+    }
+
+    #[test]
+    fn should_report_reconnect_when_endpoint_changes() {
+        let states = run_summarized(&[
+            Step::running(1234, &["127.0.0.1"]),
+            Step::running(1234, &["127.0.0.2"]),
+            Step::running(1234, &["127.0.0.2"])
+        ]);
+
+        assert_eq!(states, vec![
+            "Running(1234)",
+            "Listening(1234, 127.0.0.1:6040, EUC)",
+            "Reconnected(1234, 127.0.0.1 -> 127.0.0.2, EUC)"
+        ]);
+    }
+
+    #[test]
+    fn should_report_reconnect_after_connection_gap() {
+        let states = run_summarized(&[
+            Step::running(1234, &["127.0.0.1"]),
+            Step::running(1234, &[]),
+            Step::running(1234, &["127.0.0.2"])
+        ]);
+
+        assert_eq!(states, vec![
+            "Running(1234)",
+            "Listening(1234, 127.0.0.1:6040, EUC)",
+            "NotListening(1234)",
+            "Reconnected(1234, 127.0.0.1 -> 127.0.0.2, EUC)"
+        ]);
+    }
+
+    #[test]
+    fn should_stay_on_current_endpoint_while_sockets_overlap() {
+        let states = run_summarized(&[
+            Step::running(1234, &["127.0.0.1"]),
+            Step::running(1234, &["127.0.0.2", "127.0.0.1"]),
+            Step::running(1234, &["127.0.0.2"])
+        ]);
+
+        assert_eq!(states, vec![
+            "Running(1234)",
+            "Listening(1234, 127.0.0.1:6040, EUC)",
+            "Reconnected(1234, 127.0.0.1 -> 127.0.0.2, EUC)"
+        ]);
+    }
+
+    #[test]
+    fn should_sample_connection_only_while_listening() {
+        let events = run_watcher(&[
+            Step::running(1234, &[]).with_rtt(1234, 30.0),
+            Step::running(1234, &["127.0.0.1"]).with_rtt(1234, 40.0),
+            Step::running(1234, &["127.0.0.1"]).with_rtt(1234, 50.0)
+        ]);
+
+        let samples: Vec<_> = events.iter()
+            .filter_map(|event| match event {
+                WatcherEvent::ConnectionSample { sample, .. } => Some(sample.rtt_ms),
+                WatcherEvent::State(_) => None,
+            })
+            .collect();
+
+        assert_eq!(samples, vec![40.0, 50.0]);
+    }
+
+    #[test]
+    fn should_sample_own_connection_of_clients_on_same_server() {
+        let events = run_watcher(&[
+            Step::running(1234, &["127.0.0.1"]).and(5678, &["127.0.0.1"]).with_rtt(1234, 40.0).with_rtt(5678, 90.0)
+        ]);
+
+        let samples: Vec<_> = events.iter()
+            .filter_map(|event| match event {
+                WatcherEvent::ConnectionSample { sample, .. } => Some(sample.rtt_ms),
+                WatcherEvent::State(_) => None,
+            })
+            .collect();
+
+        assert_eq!(samples, vec![40.0, 90.0]);
+    }
+}
@@ -1,32 +1,56 @@
-use std::{sync::{Arc, Mutex}, thread::JoinHandle, time::Duration};
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, thread::JoinHandle, time::Duration};
 
 use chrono::Utc;
-use log::debug;
-use tauri::{AppHandle, Emitter};
+use log::{debug, error};
+use tauri::{AppHandle, Emitter, EventId, Listener};
 use anyhow::*;
 use tokio::{runtime::Runtime, time::sleep};
 use uuid::Uuid;
 
-use crate::{fake_encounter::FakeEncounter, models::{Boss, Encounter, Player}};
+use crate::{fake_encounter::FakeEncounter, models::{Boss, ConnectionQuality, ConnectionSample, Encounter, Player}};
 
+/// Parses the traffic of a single game client instance.
 pub struct Processor {
     app_handle: AppHandle,
-    handle: Option<JoinHandle<()>>
+    instance_id: Uuid,
+    handle: Option<JoinHandle<()>>,
+    close_flag: Arc<AtomicBool>,
+    reset_flag: Arc<AtomicBool>,
+    reset_listener: Option<EventId>,
+    connection_quality: Arc<Mutex<ConnectionQuality>>
 }
 
 impl Processor {
-    pub fn new(app_handle: AppHandle) -> Self {
+    pub fn new(app_handle: AppHandle, instance_id: Uuid) -> Self {
         Self { 
             app_handle,
-            handle: None
+            instance_id,
+            handle: None,
+            close_flag: Arc::new(AtomicBool::new(false)),
+            reset_flag: Arc::new(AtomicBool::new(false)),
+            reset_listener: None,
+            connection_quality: Arc::new(Mutex::new(ConnectionQuality::default()))
         }
     }
 
     pub fn start(&mut self, region: String) {
 
-        debug!("start");
+        debug!("start {}", self.instance_id);
         let app_handle = self.app_handle.clone();
+        let instance_id = self.instance_id;
+        let close_flag = self.close_flag.clone();
+        let reset_flag = self.reset_flag.clone();
+        let connection_quality = self.connection_quality.clone();
         let duration  = Duration::from_secs(1);
+
+        {
+            let reset_flag = self.reset_flag.clone();
+            self.reset_listener = Some(self.app_handle.listen_any("reset-encounter", move |event| {
+                if serde_json::from_str::<Uuid>(event.payload()).ok() == Some(instance_id) {
+                    reset_flag.store(true, Ordering::Relaxed);
+                }
+            }));
+        }
         
         // TO-DO Download dll from https://github.com/averageeucplayer/lost-metrics-sniffer/releases/latest
         // Then follow logic as in lost-metrics-console
@@ -35,11 +59,26 @@ impl Processor {
             let rt = Runtime::new().expect("Failed to create runtime");
 
             rt.block_on(async {
-                let mut fake_encounter = FakeEncounter::new();
+                let mut fake_encounter = FakeEncounter::new(instance_id);
+
+                while !close_flag.load(Ordering::Relaxed) {
+
+                    if reset_flag.swap(false, Ordering::Relaxed) {
+                        if let Err(err) = app_handle.emit("encounter-closed", fake_encounter.get()) {
+                            error!("Could not emit encounter-closed: {}", err);
+                        }
 
-                loop {
+                        // the new encounter starts its own connection statistics
+                        *connection_quality.lock().unwrap() = ConnectionQuality::default();
+                        fake_encounter = FakeEncounter::new(instance_id);
+                    }
     
                     fake_encounter.tick();
+
+                    let quality = connection_quality.lock().unwrap().clone();
+                    if quality.samples > 0 {
+                        fake_encounter.set_connection_quality(quality);
+                    }
     
                     let encounter = fake_encounter.get();
         
@@ -47,12 +86,25 @@ impl Processor {
         
                     sleep(duration).await;
                 }
+
+                app_handle.emit("encounter-closed", fake_encounter.get()).unwrap();
             })
         });
         self.handle = Some(handle);
     }
 
+    /// Adds a sample of the game connection to the current encounter and returns the aggregate.
+    pub fn record_sample(&self, sample: &ConnectionSample) -> ConnectionQuality {
+        let mut connection_quality = self.connection_quality.lock().unwrap();
+        connection_quality.record(sample);
+        connection_quality.clone()
+    }
+
     pub async fn stop(&mut self) -> Result<()> {
+        self.close_flag.store(true, Ordering::Relaxed);
+        if let Some(reset_listener) = self.reset_listener.take() {
+            self.app_handle.unlisten(reset_listener);
+        }
         if let Some(handle) = self.handle.take() {
             handle.join()
                 .map_err(|err| anyhow::anyhow!("{:?}", err))?;
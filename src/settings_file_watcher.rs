@@ -0,0 +1,53 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use log::*;
+use tauri::{AppHandle, Emitter};
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::{models::SettingsFileError, settings_manager::SettingsManager};
+
+/// How often the modification time of `settings.json` is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Editors save in several steps, an edit is loaded once the file stopped changing for this long.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Polls the settings file for edits made outside of the app and loads them,
+/// subscribers of [`SettingsManager::subscribe`] reconfigure the app as for saved settings.
+pub fn watch_settings_file(app_handle: AppHandle, settings_manager: Arc<Mutex<SettingsManager>>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            sleep(POLL_INTERVAL).await;
+
+            let Some(mut modified_on) = settings_manager.lock().await.external_modification().await else {
+                continue;
+            };
+
+            loop {
+                sleep(DEBOUNCE).await;
+
+                match settings_manager.lock().await.external_modification().await {
+                    Some(latest) if latest != modified_on => modified_on = latest,
+                    _ => break,
+                }
+            }
+
+            let mut settings_manager = settings_manager.lock().await;
+
+            if let Err(err) = settings_manager.reload().await {
+                warn!("Ignoring invalid settings file: {:#}", err);
+
+                let error = SettingsFileError {
+                    path: settings_manager.path().to_path_buf(),
+                    message: format!("{:#}", err),
+                    occurred_on: Utc::now()
+                };
+
+                if let Err(err) = app_handle.emit("settings-file-invalid", error) {
+                    error!("Could not emit settings-file-invalid: {}", err);
+                }
+            }
+        }
+    });
+}
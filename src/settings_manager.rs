@@ -1,50 +1,373 @@
-use std::{error::Error, path::PathBuf};
+use std::{error::Error, ffi::OsString, path::{Path, PathBuf}, time::SystemTime};
 
-use tokio::fs::File;
+use anyhow::{anyhow, Context};
+use log::*;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{fs::{self, File}, io::AsyncWriteExt, sync::watch};
 
-use crate::models::Settings;
+use crate::{error::AppError, models::{FieldError, ProfilesResult, Settings, SettingsFile}, settings_migration::{self, CURRENT_SCHEMA_VERSION}, settings_overrides::SettingsOverrides};
 
+/// Number of previous settings files kept as `settings.json.bak.1` (newest) to `.bak.N`.
+const BACKUP_COUNT: usize = 3;
 
+/// Loads and saves the settings file, keeping it cached in memory.
+///
+/// Everything outside of the profile commands works with the [`Settings`] of the active profile,
+/// with the startup [`SettingsOverrides`] applied.
 pub struct SettingsManager {
     path: PathBuf,
-    settings: Option<Settings>
+    file: Option<SettingsFile>,
+    overrides: SettingsOverrides,
+    warnings: Vec<String>,
+    changes: watch::Sender<Settings>,
+    /// Modification time of the file when it was last read or written by the app.
+    synced_on: Option<SystemTime>
 }
 
 impl SettingsManager {
     pub fn new(path: PathBuf) -> Self {
+        Self::with_overrides(path, SettingsOverrides::default())
+    }
+
+    pub fn with_overrides(path: PathBuf, overrides: SettingsOverrides) -> Self {
         Self { 
             path,
-            settings: None
+            file: None,
+            overrides,
+            warnings: vec![],
+            changes: watch::channel(Settings::default()).0,
+            synced_on: None
         }
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn overrides(&self) -> &SettingsOverrides {
+        &self.overrides
+    }
+
+    /// Receives the settings of the active profile every time they are loaded, saved or another profile is activated.
+    pub fn subscribe(&self) -> watch::Receiver<Settings> {
+        self.changes.subscribe()
+    }
+
+    /// Problems found while loading the settings that the user should be told about.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Saves the settings of the active profile, overridden fields keep their stored values.
     pub async fn save(&mut self, settings: &Settings) -> Result<(), Box<dyn Error>> {
-        let file = File::create(&self.path).await?;
-        serde_json::to_writer_pretty(file.into_std().await, &settings)?;
+        let mut file = self.load_cached().await?;
+        let settings = self.overrides.unapply(settings, active_settings(&file)?);
+        file.profiles.insert(file.active_profile.clone(), settings);
+        self.write(file, true).await?;
 
         Ok(())
     }
 
+    /// Settings of the active profile.
     pub async fn get_or_create_default(&mut self) -> Result<Settings, Box<dyn Error>> {
+        let file = self.load_cached().await?;
+        Ok(self.overrides.apply(active_settings(&file)?))
+    }
+
+    /// Settings of the active profile as stored, without the startup overrides.
+    pub async fn get_stored(&mut self) -> Result<Settings, Box<dyn Error>> {
+        let file = self.load_cached().await?;
+        Ok(active_settings(&file)?.clone())
+    }
+
+    pub async fn get_profiles(&mut self) -> Result<ProfilesResult, AppError> {
+        Ok(self.load_cached().await?.profiles())
+    }
+
+    /// Adds a profile with the default settings.
+    pub async fn create_profile(&mut self, name: &str) -> Result<ProfilesResult, AppError> {
+        let settings = active_settings(&default_settings()?)?.clone();
+        self.add_profile(name, settings).await
+    }
+
+    pub async fn clone_profile(&mut self, source: &str, name: &str) -> Result<ProfilesResult, AppError> {
+        let file = self.load_cached().await?;
+        let settings = file.profiles.get(source)
+            .cloned()
+            .ok_or_else(|| AppError::UnknownProfile(source.to_string()))?;
+
+        self.add_profile(name, settings).await
+    }
+
+    pub async fn rename_profile(&mut self, name: &str, new_name: &str) -> Result<ProfilesResult, AppError> {
+        let new_name = validate_profile_name(new_name)?;
+        let mut file = self.load_cached().await?;
 
-        if let Some(settings) = self.settings.clone() {
-            return Ok(settings);
+        if file.profiles.contains_key(&new_name) {
+            return Err(AppError::ProfileExists(new_name));
         }
-        
-        if self.path.exists() {
-            let file = File::open(&self.path).await?;
-            let settings = serde_json::from_reader(file.into_std().await)?;
-            return Ok(settings);
+
+        let settings = file.profiles.remove(name)
+            .ok_or_else(|| AppError::UnknownProfile(name.to_string()))?;
+        file.profiles.insert(new_name.clone(), settings);
+
+        if file.active_profile == name {
+            file.active_profile = new_name;
         }
 
-        let bytes = include_bytes!("../default_settings.json");
-        let settings = serde_json::from_slice(bytes)?;
+        Ok(self.write(file, false).await?)
+    }
+
+    /// Deletes an inactive profile, activate another one first to delete the active profile.
+    pub async fn delete_profile(&mut self, name: &str) -> Result<ProfilesResult, AppError> {
+        let mut file = self.load_cached().await?;
+
+        if file.active_profile == name {
+            return Err(AppError::ActiveProfile(name.to_string()));
+        }
+
+        if file.profiles.remove(name).is_none() {
+            return Err(AppError::UnknownProfile(name.to_string()));
+        }
+
+        Ok(self.write(file, false).await?)
+    }
+
+    /// Switches to another profile, subscribers receive its settings.
+    pub async fn activate_profile(&mut self, name: &str) -> Result<ProfilesResult, AppError> {
+        let mut file = self.load_cached().await?;
+
+        if !file.profiles.contains_key(name) {
+            return Err(AppError::UnknownProfile(name.to_string()));
+        }
+
+        file.active_profile = name.to_string();
+        Ok(self.write(file, true).await?)
+    }
+
+    async fn add_profile(&mut self, name: &str, settings: Settings) -> Result<ProfilesResult, AppError> {
+        let name = validate_profile_name(name)?;
+        let mut file = self.load_cached().await?;
+
+        if file.profiles.contains_key(&name) {
+            return Err(AppError::ProfileExists(name));
+        }
+
+        file.profiles.insert(name, settings);
+        Ok(self.write(file, false).await?)
+    }
+
+    /// Writes the file after rotating the backups, `notify` sends the active settings to subscribers.
+    async fn write(&mut self, mut file: SettingsFile, notify: bool) -> anyhow::Result<ProfilesResult> {
+        file.schema_version = CURRENT_SCHEMA_VERSION;
+
+        self.rotate_backups().await?;
+        write_atomic(&self.path, &file).await?;
+        self.synced_on = self.modified_on().await;
+        self.set_cached(file, notify)
+    }
+
+    fn set_cached(&mut self, file: SettingsFile, notify: bool) -> anyhow::Result<ProfilesResult> {
+        let profiles = file.profiles();
+
+        if notify {
+            self.changes.send_replace(self.overrides.apply(active_settings(&file)?));
+        }
+
+        self.file = Some(file);
+
+        Ok(profiles)
+    }
+
+    /// Cached settings file, read from disk or created with the defaults on first use.
+    async fn load_cached(&mut self) -> anyhow::Result<SettingsFile> {
+        if let Some(file) = self.file.clone() {
+            return Ok(file);
+        }
         
-        let file = File::create(&self.path).await?;
-        serde_json::to_writer_pretty(file.into_std().await, &settings)?;
+        let file = if self.path.exists() {
+            let loaded = self.load().await.map_err(|err| format!("{:#}", err));
+
+            match loaded {
+                Ok(file) => file,
+                Err(reason) => self.recover(reason).await?,
+            }
+        } else {
+            let file = default_settings()?;
+            write_atomic(&self.path, &file).await?;
+            file
+        };
+
+        self.synced_on = self.modified_on().await;
+        self.set_cached(file.clone(), true)?;
+
+        Ok(file)
+    }
+
+    async fn modified_on(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).await.ok()?.modified().ok()
+    }
+
+    /// Modification time of the file if it was changed by something other than the app.
+    pub async fn external_modification(&self) -> Option<SystemTime> {
+        let modified_on = self.modified_on().await?;
+        (Some(modified_on) != self.synced_on).then_some(modified_on)
+    }
+
+    /// Loads a file edited outside of the app, returns whether the settings changed.
+    ///
+    /// An invalid file is reported once and the cached settings stay active until the file is fixed.
+    pub async fn reload(&mut self) -> anyhow::Result<bool> {
+        self.synced_on = self.modified_on().await;
+
+        let bytes = fs::read(&self.path).await?;
+        let (value, _) = parse(&bytes)?;
+        let file: SettingsFile = serde_json::from_value(value)?;
+
+        if self.file.as_ref() == Some(&file) {
+            return Ok(false);
+        }
+
+        info!("Loaded settings edited outside of the app");
+        self.set_cached(file, true)?;
+
+        Ok(true)
+    }
+
+    /// Reads the settings file, upgrading it to the current schema after backing up the original.
+    async fn load(&mut self) -> anyhow::Result<SettingsFile> {
+        let bytes = fs::read(&self.path).await?;
+        let (value, from_version) = parse(&bytes)?;
+
+        if from_version != CURRENT_SCHEMA_VERSION {
+            let backup_path = self.sibling(&format!(".v{}.bak", from_version));
+
+            info!("Migrating settings from schema {} to {}, backup at {:?}", from_version, CURRENT_SCHEMA_VERSION, backup_path);
+            fs::write(&backup_path, &bytes).await?;
+            write_atomic(&self.path, &value).await?;
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Replaces an unreadable settings file with the newest readable backup or the defaults,
+    /// keeping the broken file as `settings.json.corrupt`.
+    async fn recover(&mut self, reason: String) -> anyhow::Result<SettingsFile> {
+        error!("Could not load {:?}: {}", self.path, reason);
+
+        let corrupt_path = self.sibling(".corrupt");
+        fs::rename(&self.path, &corrupt_path).await?;
+
+        for index in 1..=BACKUP_COUNT {
+            let backup_path = self.backup_path(index);
+
+            let Ok(bytes) = fs::read(&backup_path).await else {
+                continue;
+            };
+
+            match parse(&bytes).and_then(|(value, _)| Ok(serde_json::from_value::<SettingsFile>(value)?)) {
+                Ok(file) => {
+                    write_atomic(&self.path, &file).await?;
+                    self.warnings.push(format!(
+                        "Settings could not be read ({}), restored the backup {:?}. The broken file was kept at {:?}.",
+                        reason, backup_path, corrupt_path));
+
+                    return Ok(file);
+                },
+                Err(err) => warn!("Skipping backup {:?}: {:#}", backup_path, err),
+            }
+        }
+
+        let file = default_settings()?;
+        write_atomic(&self.path, &file).await?;
+        self.warnings.push(format!(
+            "Settings could not be read ({}), defaults were restored. The broken file was kept at {:?}.",
+            reason, corrupt_path));
+
+        Ok(file)
+    }
+
+    async fn rotate_backups(&self) -> anyhow::Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        for index in (1..BACKUP_COUNT).rev() {
+            let backup_path = self.backup_path(index);
+
+            if backup_path.exists() {
+                fs::rename(&backup_path, self.backup_path(index + 1)).await?;
+            }
+        }
+
+        fs::copy(&self.path, self.backup_path(1)).await?;
+
+        Ok(())
+    }
+
+    fn backup_path(&self, index: usize) -> PathBuf {
+        self.sibling(&format!(".bak.{}", index))
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(suffix);
+        path.into()
+    }
+}
+
+fn default_settings() -> anyhow::Result<SettingsFile> {
+    Ok(serde_json::from_slice(include_bytes!("../default_settings.json"))?)
+}
+
+fn active_settings(file: &SettingsFile) -> anyhow::Result<&Settings> {
+    file.active_settings().ok_or_else(|| anyhow!("active profile {:?} does not exist", file.active_profile))
+}
+
+fn validate_profile_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+
+    let message = if name.is_empty() {
+        "profile name must not be empty".to_string()
+    } else if name.chars().count() > SettingsFile::MAX_PROFILE_NAME_LEN {
+        format!("profile name must be at most {} characters", SettingsFile::MAX_PROFILE_NAME_LEN)
+    } else {
+        return Ok(name.to_string());
+    };
 
-        Ok(settings)
+    Err(AppError::Validation(vec![FieldError::new("name", message)]))
+}
+
+/// Parses and migrates a settings file, rejecting settings that would not pass validation.
+pub fn parse(bytes: &[u8]) -> anyhow::Result<(Value, u32)> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    let from_version = settings_migration::migrate(&mut value)?;
+    let file: SettingsFile = serde_json::from_value(value.clone())?;
+
+    if let Err(errors) = file.validate() {
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        return Err(anyhow!("invalid fields {}", fields.join(", ")));
     }
+
+    Ok((value, from_version))
+}
+
+/// Writes through a temporary file renamed over `path`, so a crash leaves either the old or the new file.
+async fn write_atomic(path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    let bytes = serde_json::to_vec_pretty(value)?;
+    let mut temp_path = OsString::from(path.as_os_str());
+    temp_path.push(".tmp");
+
+    let mut file = File::create(&temp_path).await
+        .with_context(|| format!("could not create {:?}", temp_path))?;
+    file.write_all(&bytes).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(&temp_path, path).await?;
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -69,4 +392,165 @@ mod tests {
 
         let result = settings_manager.get_or_create_default().await.unwrap();
     }
-}
\ No newline at end of file
+
+    fn temp_settings_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lost-metrics-{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("settings.json")
+    }
+
+    #[tokio::test]
+    async fn should_rotate_backups_on_save() {
+        let path = temp_settings_path();
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let mut settings = settings_manager.get_or_create_default().await.unwrap();
+
+        for port in 1..=BACKUP_COUNT as u16 + 1 {
+            settings.sniffer.port = port;
+            settings_manager.save(&settings).await.unwrap();
+        }
+
+        let backup: SettingsFile = serde_json::from_slice(&std::fs::read(settings_manager.backup_path(1)).unwrap()).unwrap();
+        assert_eq!(backup.active_settings().unwrap().sniffer.port, BACKUP_COUNT as u16);
+        assert!(!settings_manager.backup_path(BACKUP_COUNT + 1).exists());
+        assert!(!settings_manager.sibling(".tmp").exists());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_restore_last_good_backup() {
+        let path = temp_settings_path();
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let mut settings = settings_manager.get_or_create_default().await.unwrap();
+        settings.sniffer.port = 7000;
+        settings_manager.save(&settings).await.unwrap();
+        settings_manager.save(&settings).await.unwrap();
+        std::fs::write(&path, "{ \"sniffer\": ").unwrap();
+
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let settings = settings_manager.get_or_create_default().await.unwrap();
+
+        assert_eq!(settings.sniffer.port, 7000);
+        assert_eq!(settings_manager.warnings().len(), 1);
+        assert!(settings_manager.sibling(".corrupt").exists());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_defaults() {
+        let path = temp_settings_path();
+        std::fs::write(&path, "not json").unwrap();
+
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let settings = settings_manager.get_or_create_default().await.unwrap();
+
+        assert_eq!(settings.sniffer.port, 6040);
+        assert_eq!(settings_manager.warnings().len(), 1);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_notify_subscribers_on_save() {
+        let path = temp_settings_path();
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let mut settings = settings_manager.get_or_create_default().await.unwrap();
+        let mut changes = settings_manager.subscribe();
+
+        settings.sniffer.port = 7000;
+        settings_manager.save(&settings).await.unwrap();
+
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(changes.borrow_and_update().sniffer.port, 7000);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_manage_profiles() {
+        let path = temp_settings_path();
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let mut settings = settings_manager.get_or_create_default().await.unwrap();
+        settings.sniffer.port = 7000;
+        settings_manager.save(&settings).await.unwrap();
+        let mut changes = settings_manager.subscribe();
+
+        settings_manager.clone_profile("Default", "Raid").await.unwrap();
+        settings_manager.create_profile("Solo").await.unwrap();
+        settings_manager.rename_profile("Raid", "Raid night").await.unwrap();
+        assert!(!changes.has_changed().unwrap());
+
+        let profiles = settings_manager.activate_profile("Raid night").await.unwrap();
+        assert_eq!(profiles.active_profile, "Raid night");
+        assert_eq!(profiles.profiles, vec!["Default", "Raid night", "Solo"]);
+        assert_eq!(changes.borrow_and_update().sniffer.port, 7000);
+
+        assert!(matches!(settings_manager.delete_profile("Raid night").await, Err(AppError::ActiveProfile(_))));
+        assert!(matches!(settings_manager.create_profile("Solo").await, Err(AppError::ProfileExists(_))));
+        assert!(matches!(settings_manager.create_profile(" ").await, Err(AppError::Validation(_))));
+        settings_manager.delete_profile("Solo").await.unwrap();
+
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let profiles = settings_manager.get_profiles().await.unwrap();
+        assert_eq!(profiles.active_profile, "Raid night");
+        assert_eq!(profiles.profiles, vec!["Default", "Raid night"]);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_not_persist_overridden_values() {
+        let path = temp_settings_path();
+        let overrides = SettingsOverrides::parse(vec!["--port".into(), "6041".into()], |_| None);
+        let mut settings_manager = SettingsManager::with_overrides(path.clone(), overrides);
+
+        let mut settings = settings_manager.get_or_create_default().await.unwrap();
+        assert_eq!(settings.sniffer.port, 6041);
+        // exports see the stored value
+        assert_eq!(settings_manager.get_stored().await.unwrap().sniffer.port, 6040);
+
+        settings.sniffer.check_interval = Duration::from_secs(5);
+        settings_manager.save(&settings).await.unwrap();
+
+        let mut settings_manager = SettingsManager::new(path.clone());
+        let settings = settings_manager.get_or_create_default().await.unwrap();
+        assert_eq!(settings.sniffer.port, 6040);
+        assert_eq!(settings.sniffer.check_interval, Duration::from_secs(5));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    fn write_external(path: &Path, contents: &str) {
+        std::fs::write(path, contents).unwrap();
+        // make the edit visible on file systems with a coarse modification time
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_reload_external_edits() {
+        let path = temp_settings_path();
+        let mut settings_manager = SettingsManager::new(path.clone());
+        settings_manager.get_or_create_default().await.unwrap();
+        let mut changes = settings_manager.subscribe();
+        assert!(settings_manager.external_modification().await.is_none());
+
+        let edited = std::fs::read_to_string(&path).unwrap().replace("6040", "7000");
+        write_external(&path, &edited);
+
+        assert!(settings_manager.external_modification().await.is_some());
+        assert!(settings_manager.reload().await.unwrap());
+        assert_eq!(changes.borrow_and_update().sniffer.port, 7000);
+        assert!(settings_manager.external_modification().await.is_none());
+
+        write_external(&path, &edited.replace("7000", "0"));
+
+        assert!(settings_manager.reload().await.is_err());
+        assert!(settings_manager.external_modification().await.is_none());
+        assert_eq!(settings_manager.get_or_create_default().await.unwrap().sniffer.port, 7000);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+/// Schema version written by this build, see [`crate::models::SettingsFile::schema_version`].
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+/// Files written before `schemaVersion` existed.
+const INITIAL_SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[n]` upgrades schema `n + 1` to `n + 2`.
+const MIGRATIONS: [Migration; (CURRENT_SCHEMA_VERSION - INITIAL_SCHEMA_VERSION) as usize] = [
+    migrate_v1_to_v2,
+    migrate_v2_to_v3
+];
+
+/// Profile the settings of files predating profiles end up in.
+const DEFAULT_PROFILE: &str = "Default";
+
+/// Schema version of a raw settings file.
+pub fn schema_version(settings: &Value) -> Result<u32> {
+    match settings.get("schemaVersion") {
+        Some(version) => version.as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .context("schemaVersion is not a valid version"),
+        None => Ok(INITIAL_SCHEMA_VERSION),
+    }
+}
+
+/// Upgrades a raw settings file to [`CURRENT_SCHEMA_VERSION`] one step at a time,
+/// returns the version it started from.
+pub fn migrate(settings: &mut Value) -> Result<u32> {
+    let from_version = schema_version(settings)?;
+
+    if from_version > CURRENT_SCHEMA_VERSION {
+        bail!("settings schema {} is newer than the supported {}", from_version, CURRENT_SCHEMA_VERSION);
+    }
+
+    let Some(object) = settings.as_object_mut() else {
+        bail!("settings must be a JSON object");
+    };
+
+    for version in from_version.max(INITIAL_SCHEMA_VERSION)..CURRENT_SCHEMA_VERSION {
+        let migration = MIGRATIONS[(version - INITIAL_SCHEMA_VERSION) as usize];
+        migration(object).with_context(|| format!("failed to migrate settings from schema {}", version))?;
+        object.insert("schemaVersion".into(), json!(version + 1));
+    }
+
+    Ok(from_version)
+}
+
+/// Replaces the single `processName` with a list of patterns and drops the unused `version`.
+fn migrate_v1_to_v2(settings: &mut Map<String, Value>) -> Result<()> {
+    settings.remove("version");
+
+    let Some(sniffer) = settings.get_mut("sniffer").and_then(Value::as_object_mut) else {
+        bail!("missing sniffer settings");
+    };
+
+    let process_names = match sniffer.remove("processName") {
+        Some(Value::String(process_name)) => vec![json!({ "type": "exact", "value": process_name })],
+        Some(value) => bail!("processName should be a string, got {}", value),
+        None => vec![],
+    };
+
+    sniffer.insert("processNames".into(), Value::Array(process_names));
+    sniffer.insert("caseSensitive".into(), json!(false));
+    sniffer.insert("matchExecutablePath".into(), json!(false));
+
+    Ok(())
+}
+
+/// Moves the settings into a single profile.
+fn migrate_v2_to_v3(settings: &mut Map<String, Value>) -> Result<()> {
+    settings.remove("schemaVersion");
+    let profile = std::mem::take(settings);
+
+    settings.insert("activeProfile".into(), json!(DEFAULT_PROFILE));
+    settings.insert("profiles".into(), json!({ DEFAULT_PROFILE: profile }));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{ProcessPattern, SettingsFile};
+
+    use super::*;
+
+    fn v1_settings() -> Value {
+        json!({
+            "version": "^0.0.1",
+            "sniffer": {
+                "processName": "client_server.exe",
+                "port": 6040,
+                "checkInterval": "10s"
+            }
+        })
+    }
+
+    #[test]
+    fn should_migrate_v1_to_v2() {
+        let mut settings = v1_settings();
+
+        migrate_v1_to_v2(settings.as_object_mut().unwrap()).unwrap();
+
+        assert_eq!(settings, json!({
+            "sniffer": {
+                "processNames": [{ "type": "exact", "value": "client_server.exe" }],
+                "caseSensitive": false,
+                "matchExecutablePath": false,
+                "port": 6040,
+                "checkInterval": "10s"
+            }
+        }));
+    }
+
+    #[test]
+    fn should_migrate_v2_to_v3() {
+        let mut settings = json!({
+            "schemaVersion": 2,
+            "sniffer": { "port": 6040 }
+        });
+
+        migrate_v2_to_v3(settings.as_object_mut().unwrap()).unwrap();
+
+        assert_eq!(settings, json!({
+            "activeProfile": "Default",
+            "profiles": {
+                "Default": { "sniffer": { "port": 6040 } }
+            }
+        }));
+    }
+
+    #[test]
+    fn should_migrate_unversioned_file_to_current_schema() {
+        let mut settings = v1_settings();
+
+        let from_version = migrate(&mut settings).unwrap();
+        let settings: SettingsFile = serde_json::from_value(settings).unwrap();
+
+        assert_eq!(from_version, INITIAL_SCHEMA_VERSION);
+        assert_eq!(settings.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(settings.active_settings().unwrap().sniffer.process_names, vec![ProcessPattern::Exact("client_server.exe".into())]);
+    }
+
+    #[test]
+    fn should_keep_default_settings_at_current_schema() {
+        let mut settings: Value = serde_json::from_slice(include_bytes!("../default_settings.json")).unwrap();
+        let before = settings.clone();
+
+        assert_eq!(migrate(&mut settings).unwrap(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(settings, before);
+    }
+
+    #[test]
+    fn should_reject_newer_schema() {
+        let mut settings = json!({ "schemaVersion": CURRENT_SCHEMA_VERSION + 1 });
+
+        assert!(migrate(&mut settings).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use humantime_serde::re::humantime;
+use log::*;
+
+use crate::models::{ProcessPattern, SettingSource, Settings, SnifferSettings};
+
+const ENV_PREFIX: &str = "LOST_METRICS_";
+
+/// Value taken from the command line or the environment instead of `settings.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override<T> {
+    pub value: T,
+    pub source: SettingSource
+}
+
+/// Startup overrides, e.g. `--port 6041` or `LOST_METRICS_PORT=6041`, the command line wins.
+///
+/// Overridden values are applied to the settings handed out but never written to the file.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsOverrides {
+    pub process_name: Option<Override<String>>,
+    pub port: Option<Override<u16>>,
+    pub check_interval: Option<Override<Duration>>,
+    pub data_dir: Option<Override<PathBuf>>,
+    /// Flags whose value could not be parsed, with that value.
+    pub invalid: Vec<(&'static str, String)>
+}
+
+impl SettingsOverrides {
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1).collect(), |name| std::env::var(name).ok())
+    }
+
+    /// Unknown arguments are ignored, other plugins pass their own flags.
+    pub fn parse(args: Vec<String>, env: impl Fn(&str) -> Option<String>) -> Self {
+        let lookup = |flag: &str| -> Option<(String, SettingSource)> {
+            find_flag(&args, flag)
+                .map(|value| (value, SettingSource::CommandLine))
+                .or_else(|| {
+                    let name = format!("{}{}", ENV_PREFIX, flag.to_ascii_uppercase().replace('-', "_"));
+                    env(&name).map(|value| (value, SettingSource::Environment))
+                })
+        };
+
+        let mut invalid = vec![];
+
+        Self {
+            process_name: parse_override(lookup("process-name"), "process-name", &mut invalid, |value| {
+                let value = value.trim();
+                (!value.is_empty()).then(|| value.to_string())
+            }),
+            port: parse_override(lookup("port"), "port", &mut invalid, |value| {
+                value.parse().ok().filter(|port| *port != 0)
+            }),
+            check_interval: parse_override(lookup("check-interval"), "check-interval", &mut invalid, |value| {
+                humantime::parse_duration(value).ok().filter(|check_interval| {
+                    (SnifferSettings::MIN_CHECK_INTERVAL..=SnifferSettings::MAX_CHECK_INTERVAL).contains(check_interval)
+                })
+            }),
+            data_dir: parse_override(lookup("data-dir"), "data-dir", &mut invalid, |value| {
+                (!value.is_empty()).then(|| PathBuf::from(value))
+            }),
+            invalid
+        }
+    }
+
+    /// Logs the overrides in use and the ignored ones, parsing happens before the logger is registered.
+    pub fn log(&self) {
+        let sources = [
+            ("process-name", self.process_name.as_ref().map(|value| value.source)),
+            ("port", self.port.as_ref().map(|value| value.source)),
+            ("check-interval", self.check_interval.as_ref().map(|value| value.source)),
+            ("data-dir", self.data_dir.as_ref().map(|value| value.source))
+        ];
+
+        for (flag, source) in sources {
+            if let Some(source) = source {
+                info!("Overriding {} from {:?}", flag, source);
+            }
+        }
+
+        for (flag, value) in &self.invalid {
+            warn!("Ignoring invalid {} override {:?}", flag, value);
+        }
+    }
+
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+
+        if let Some(process_name) = &self.process_name {
+            settings.sniffer.process_names = vec![ProcessPattern::Exact(process_name.value.clone())];
+        }
+
+        if let Some(port) = &self.port {
+            settings.sniffer.port = port.value;
+        }
+
+        if let Some(check_interval) = &self.check_interval {
+            settings.sniffer.check_interval = check_interval.value;
+        }
+
+        settings
+    }
+
+    /// Puts the stored values back into overridden fields before `settings` are saved.
+    pub fn unapply(&self, settings: &Settings, stored: &Settings) -> Settings {
+        let mut settings = settings.clone();
+
+        if self.process_name.is_some() {
+            settings.sniffer.process_names = stored.sniffer.process_names.clone();
+        }
+
+        if self.port.is_some() {
+            settings.sniffer.port = stored.sniffer.port;
+        }
+
+        if self.check_interval.is_some() {
+            settings.sniffer.check_interval = stored.sniffer.check_interval;
+        }
+
+        settings
+    }
+
+    /// Source of every value that can be overridden, keyed by its camelCase path.
+    pub fn sources(&self) -> BTreeMap<String, SettingSource> {
+        [
+            ("sniffer.processNames", self.process_name.as_ref().map(|value| value.source)),
+            ("sniffer.port", self.port.as_ref().map(|value| value.source)),
+            ("sniffer.checkInterval", self.check_interval.as_ref().map(|value| value.source)),
+            ("dataDir", self.data_dir.as_ref().map(|value| value.source))
+        ]
+        .into_iter()
+        .map(|(field, source)| (field.to_string(), source.unwrap_or(SettingSource::File)))
+        .collect()
+    }
+}
+
+/// Value of `--flag value` or `--flag=value`, the last occurrence wins.
+fn find_flag(args: &[String], flag: &str) -> Option<String> {
+    let name = format!("--{}", flag);
+    let mut value = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if *arg == name {
+            value = args.next().cloned();
+        } else if let Some(inline) = arg.strip_prefix(&name).and_then(|rest| rest.strip_prefix('=')) {
+            value = Some(inline.to_string());
+        }
+    }
+
+    value
+}
+
+fn parse_override<T>(
+    value: Option<(String, SettingSource)>,
+    flag: &'static str,
+    invalid: &mut Vec<(&'static str, String)>,
+    parse: impl Fn(&str) -> Option<T>) -> Option<Override<T>> {
+    let (value, source) = value?;
+    let parsed = parse(&value);
+
+    if parsed.is_none() {
+        invalid.push((flag, value));
+    }
+
+    parsed.map(|value| Override { value, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn parse(args: &[&str], env: &[(&str, &str)]) -> SettingsOverrides {
+        let env: HashMap<String, String> = env.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        SettingsOverrides::parse(args.iter().map(|arg| arg.to_string()).collect(), |name| env.get(name).cloned())
+    }
+
+    #[test]
+    fn should_prefer_command_line_over_environment() {
+        let overrides = parse(
+            &["--autostart", "--port", "6041", "--process-name=game.exe"],
+            &[("LOST_METRICS_PORT", "7000"), ("LOST_METRICS_CHECK_INTERVAL", "2s")]);
+
+        assert_eq!(overrides.port, Some(Override { value: 6041, source: SettingSource::CommandLine }));
+        assert_eq!(overrides.process_name.unwrap().value, "game.exe");
+        assert_eq!(overrides.check_interval, Some(Override { value: Duration::from_secs(2), source: SettingSource::Environment }));
+        assert!(overrides.data_dir.is_none());
+    }
+
+    #[test]
+    fn should_ignore_invalid_values() {
+        let overrides = parse(&["--port", "0", "--check-interval", "0s"], &[]);
+
+        assert!(overrides.port.is_none());
+        assert!(overrides.check_interval.is_none());
+        assert_eq!(overrides.invalid, vec![("port", "0".to_string()), ("check-interval", "0s".to_string())]);
+    }
+
+    #[test]
+    fn should_keep_stored_values_of_overridden_fields() {
+        let overrides = parse(&["--port", "6041"], &[]);
+        let mut stored = Settings::default();
+        stored.sniffer.port = 6040;
+
+        let mut effective = overrides.apply(&stored);
+        assert_eq!(effective.sniffer.port, 6041);
+
+        effective.sniffer.check_interval = Duration::from_secs(5);
+        let saved = overrides.unapply(&effective, &stored);
+
+        assert_eq!(saved.sniffer.port, 6040);
+        assert_eq!(saved.sniffer.check_interval, Duration::from_secs(5));
+        assert_eq!(overrides.sources()["sniffer.port"], SettingSource::CommandLine);
+        assert_eq!(overrides.sources()["sniffer.checkInterval"], SettingSource::File);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::{models::{ImportPreview, Settings, SettingsChange, SettingsFile}, settings_migration::{self, CURRENT_SCHEMA_VERSION}};
+
+/// Builds a portable settings file holding only `settings` as profile `profile_name`,
+/// returns it with the paths of the secrets that were left out.
+pub fn export(settings: &Settings, profile_name: &str, include_secrets: bool) -> Result<(Value, Vec<String>)> {
+    let file = SettingsFile {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        active_profile: profile_name.to_string(),
+        profiles: BTreeMap::from([(profile_name.to_string(), settings.clone())])
+    };
+
+    let mut value = serde_json::to_value(&file)?;
+    let mut stripped = vec![];
+
+    if !include_secrets {
+        if let Some(profile) = value.pointer_mut(&format!("/profiles/{}", escape_pointer(profile_name))) {
+            stripped = strip_secrets(profile, Settings::SECRET_FIELDS);
+        }
+    }
+
+    Ok((value, stripped))
+}
+
+/// Reads an exported file, older schemas are migrated, and compares its settings to `current`.
+///
+/// Secrets missing from the file keep their current values.
+pub fn preview_import(bytes: &[u8], current: &Settings) -> Result<ImportPreview> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    let schema_version = settings_migration::migrate(&mut value)?;
+    let current = serde_json::to_value(current)?;
+
+    for profile in profiles_mut(&mut value) {
+        restore_secrets(profile, &current, Settings::SECRET_FIELDS);
+    }
+
+    let file: SettingsFile = serde_json::from_value(value)?;
+    let settings = file.active_settings()
+        .cloned()
+        .ok_or_else(|| anyhow!("the file has no profile {:?}", file.active_profile))?;
+
+    let mut changes = vec![];
+    diff(&current, &serde_json::to_value(&settings)?, "", &mut changes);
+
+    Ok(ImportPreview {
+        schema_version,
+        profile_name: file.active_profile,
+        field_errors: settings.validate().err().unwrap_or_default(),
+        settings,
+        changes
+    })
+}
+
+fn profiles_mut(file: &mut Value) -> Vec<&mut Value> {
+    match file.get_mut("profiles").and_then(Value::as_object_mut) {
+        Some(profiles) => profiles.values_mut().collect(),
+        None => vec![],
+    }
+}
+
+/// Removes the `fields` present in `profile`, returns the removed ones.
+fn strip_secrets(profile: &mut Value, fields: &[&str]) -> Vec<String> {
+    let mut stripped = vec![];
+
+    for field in fields {
+        let (parent, key) = match field.rsplit_once('.') {
+            Some((parent, key)) => (to_pointer(parent), key),
+            None => (String::new(), *field),
+        };
+
+        let removed = profile.pointer_mut(&parent)
+            .and_then(Value::as_object_mut)
+            .and_then(|object| object.remove(key));
+
+        if removed.is_some() {
+            stripped.push(field.to_string());
+        }
+    }
+
+    stripped
+}
+
+/// Copies the `fields` missing from an imported profile from the current settings.
+fn restore_secrets(imported: &mut Value, current: &Value, fields: &[&str]) {
+    for field in fields {
+        let pointer = to_pointer(field);
+
+        if imported.pointer(&pointer).is_some() {
+            continue;
+        }
+
+        let Some(current) = current.pointer(&pointer) else {
+            continue;
+        };
+
+        let Some((parent, key)) = pointer.rsplit_once('/') else {
+            continue;
+        };
+
+        if let Some(object) = imported.pointer_mut(parent).and_then(Value::as_object_mut) {
+            object.insert(key.replace("~1", "/").replace("~0", "~"), current.clone());
+        }
+    }
+}
+
+/// Compares object members recursively, anything else is compared as a whole.
+fn diff(current: &Value, imported: &Value, path: &str, changes: &mut Vec<SettingsChange>) {
+    if let (Value::Object(current), Value::Object(imported)) = (current, imported) {
+        let keys: BTreeSet<_> = current.keys().chain(imported.keys()).collect();
+
+        for key in keys {
+            let path = join(path, key);
+
+            match (current.get(key), imported.get(key)) {
+                (Some(current), Some(imported)) => diff(current, imported, &path, changes),
+                (current, imported) => changes.push(SettingsChange {
+                    field: path,
+                    current: current.cloned(),
+                    imported: imported.cloned()
+                }),
+            }
+        }
+
+        return;
+    }
+
+    if current != imported {
+        changes.push(SettingsChange {
+            field: path.to_string(),
+            current: Some(current.clone()),
+            imported: Some(imported.clone())
+        });
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Escapes a key for use in a JSON pointer (RFC 6901).
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// JSON pointer of a dotted field path such as `sniffer.port`.
+fn to_pointer(field: &str) -> String {
+    field.split('.').map(|key| format!("/{}", escape_pointer(key))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn default_settings() -> Settings {
+        let file: SettingsFile = serde_json::from_slice(include_bytes!("../default_settings.json")).unwrap();
+        file.active_settings().unwrap().clone()
+    }
+
+    #[test]
+    fn should_strip_marked_fields() {
+        let mut profile = serde_json::to_value(default_settings()).unwrap();
+
+        let stripped = strip_secrets(&mut profile, &["shortcuts.copySummary", "logging.retentionDays", "sniffer.missing"]);
+
+        assert_eq!(stripped, vec!["shortcuts.copySummary", "logging.retentionDays"]);
+        assert!(profile.pointer("/shortcuts/copySummary").is_none());
+        assert!(profile.pointer("/logging/retentionDays").is_none());
+        assert_eq!(profile["sniffer"]["port"], json!(6040));
+    }
+
+    #[test]
+    fn should_keep_current_secrets_on_import() {
+        let mut current = default_settings();
+        current.shortcuts.copy_summary = Some("Ctrl+Shift+C".into());
+        let current = serde_json::to_value(current).unwrap();
+        let mut imported = serde_json::to_value(default_settings()).unwrap();
+        imported["shortcuts"].as_object_mut().unwrap().remove("copySummary");
+        imported["logging"]["retentionDays"] = json!(30);
+
+        restore_secrets(&mut imported, &current, &["shortcuts.copySummary", "logging.retentionDays"]);
+
+        let imported: Settings = serde_json::from_value(imported).unwrap();
+        assert_eq!(imported.shortcuts.copy_summary.as_deref(), Some("Ctrl+Shift+C"));
+        assert_eq!(imported.logging.retention_days, 30);
+    }
+
+    #[test]
+    fn should_export_every_field_of_settings_without_secrets() {
+        let settings = default_settings();
+
+        let (exported, stripped) = export(&settings, "Default", false).unwrap();
+        let file: SettingsFile = serde_json::from_value(exported).unwrap();
+
+        assert!(Settings::SECRET_FIELDS.is_empty());
+        assert!(stripped.is_empty());
+        assert_eq!(file.active_settings(), Some(&settings));
+    }
+
+    #[test]
+    fn should_preview_changes_of_exported_file() {
+        let current = default_settings();
+        let mut exported_settings = current.clone();
+        exported_settings.sniffer.port = 7000;
+        let (exported, _) = export(&exported_settings, "Raid night", false).unwrap();
+
+        let preview = preview_import(&serde_json::to_vec(&exported).unwrap(), &current).unwrap();
+
+        assert_eq!(preview.profile_name, "Raid night");
+        assert_eq!(preview.settings.sniffer.port, 7000);
+        assert_eq!(preview.changes, vec![SettingsChange {
+            field: "sniffer.port".into(),
+            current: Some(json!(6040)),
+            imported: Some(json!(7000))
+        }]);
+    }
+
+    #[test]
+    fn should_import_older_schema() {
+        let current = default_settings();
+        let v1 = json!({
+            "version": "^0.0.1",
+            "sniffer": { "processName": "client_server.exe", "port": 6040, "checkInterval": "10s" }
+        });
+
+        let preview = preview_import(&serde_json::to_vec(&v1).unwrap(), &current).unwrap();
+
+        assert_eq!(preview.schema_version, 1);
+        assert!(preview.changes.is_empty());
+    }
+
+    #[test]
+    fn should_reject_newer_schema() {
+        let newer = json!({ "schemaVersion": CURRENT_SCHEMA_VERSION + 1 });
+
+        assert!(preview_import(&serde_json::to_vec(&newer).unwrap(), &default_settings()).is_err());
+    }
+}
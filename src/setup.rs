@@ -6,37 +6,68 @@ use chrono::Utc;
 use log::{debug, error, info};
 use lost_metrics_simulator::simulator::Simulator;
 use tauri::{async_runtime::JoinHandle, App, AppHandle, Emitter, Listener, Manager};
-use tokio::{runtime::{Handle, Runtime}, sync::Mutex, task};
-use crate::{app_ready_state::AppReadyState, background_worker::BackgroundWorker, models::*, process_watcher::{self, ProcessWatcher}, processor::Processor, settings_manager::{self, SettingsManager}, updater::*};
+use tokio::{runtime::{Handle, Runtime}, sync::{watch, Mutex}, task};
+use crate::{app_paths::{self, AppPaths}, app_ready_state::AppReadyState, autostart::{apply_autostart, launched_at_login, reconcile_autostart}, background_worker::BackgroundWorker, class_registry, instance_registry::InstanceRegistry, logging::{prune_logs, set_log_levels}, models::*, process_watcher::{self, ProcessWatcher}, processor::Processor, settings_manager::{self, SettingsManager}, settings_file_watcher::watch_settings_file, settings_overrides::SettingsOverrides, shortcuts::{register_shortcuts, ShortcutRegistry}, tray::setup_tray, updater::*, window_layout::apply_window_settings};
 
-pub fn setup_app(app: &mut App) -> Result<(), Box<dyn Error>> {
+pub fn setup_app(app: &mut App, overrides: SettingsOverrides) -> Result<(), Box<dyn Error>> {
     #[cfg(debug_assertions)]
     {
         let window = app.get_webview_window("main").unwrap();
         window.open_devtools();
     }
 
+    overrides.log();
+    let app_paths = AppPaths::resolve(
+        app.handle(),
+        overrides.data_dir.as_ref().map(|data_dir| data_dir.value.clone()))?;
+
+    if let Err(err) = app_paths.migrate_legacy_files(&app_paths::legacy_dirs()) {
+        error!("Could not migrate legacy files: {:?}", err);
+    }
+
     let rt = Handle::current();
-    let mut settings_manager = SettingsManager::new("settings.json".into());
+    let mut settings_manager = SettingsManager::with_overrides(app_paths.settings_file(), overrides);
 
     let settings = task::block_in_place(|| {
         rt.block_on(async { settings_manager.get_or_create_default().await })
     })?;
 
+    set_log_levels(&settings.logging);
+    prune_old_logs(&app_paths, settings.logging.retention_days);
+
+    setup_tray(app)?;
+    apply_window_settings(app.handle(), &settings.windows);
+
+    if launched_at_login() && settings.autostart.start_minimized {
+        if let Some(window) = app.get_webview_window("main") {
+            window.hide()?;
+        }
+    }
+
+    let settings_rx = settings_manager.subscribe();
     let settings_manager = Arc::new(Mutex::new(settings_manager));
 
     let app_handle = app.handle().clone();
     let version = app_handle.package_info().version.clone();
     let app_updater = AppUpdater::new(app_handle.clone());
     let app_updater: Arc<Mutex<AppUpdater>> = Arc::new(Mutex::new(app_updater));
-    let process_watcher: Arc<Mutex<ProcessWatcher>> = Arc::new(Mutex::new(ProcessWatcher::new(settings.sniffer.check_interval)));
+    // debug builds talk to the local simulator, which only the fake ranges cover
+    let ip_ranges_file = (!cfg!(debug_assertions)).then(|| app_paths.ip_ranges_file());
+    let process_watcher: Arc<Mutex<ProcessWatcher>> = Arc::new(Mutex::new(ProcessWatcher::new(settings.sniffer.check_interval, ip_ranges_file)));
    
     let simulator = Arc::new(Simulator::new());
     let app_ready_state: Arc<AppReadyState> = Arc::new(AppReadyState::new());
+    let instance_registry = Arc::new(Mutex::new(InstanceRegistry::new()));
         
+    app.manage(Arc::new(app_paths));
     app.manage(simulator.clone());
     app.manage(settings_manager.clone());
     app.manage(app_ready_state.clone());
+    app.manage(instance_registry.clone());
+    app.manage(process_watcher.clone());
+    app.manage(Arc::new(ShortcutRegistry::new()));
+
+    register_shortcuts(app.handle(), &settings.shortcuts);
 
     setup_update_checker_callbacks(
         app_handle.clone(),
@@ -48,10 +79,140 @@ pub fn setup_app(app: &mut App) -> Result<(), Box<dyn Error>> {
         app_updater,
         process_watcher,
         app_ready_state,
-        settings.sniffer
+        instance_registry,
+        settings.sniffer.clone()
     );
     background_worker.start();
- 
+    let background_worker = Arc::new(Mutex::new(background_worker));
+    app.manage(background_worker.clone());
+
+    setup_settings_listener(app.handle().clone(), settings_rx, settings, background_worker);
+    watch_settings_file(app.handle().clone(), settings_manager.clone());
+    setup_autostart_reconciliation(app.handle().clone(), settings_manager);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Matches the launch at login setting to the OS entry, runs after the settings listener is up
+/// so a setting turned off here is applied and forwarded like any other save.
+fn setup_autostart_reconciliation(app_handle: AppHandle, settings_manager: Arc<Mutex<SettingsManager>>) {
+    tauri::async_runtime::spawn(async move {
+        let result = async {
+            let mut settings_manager = settings_manager.lock().await;
+            let mut settings = settings_manager.get_or_create_default().await
+                .map_err(|err| anyhow::anyhow!("{}", err))?;
+            let data_dir_overridden = settings_manager.overrides().data_dir.is_some();
+            let enabled = reconcile_autostart(&app_handle, &settings.autostart, data_dir_overridden)?;
+
+            if enabled != settings.autostart.enabled {
+                settings.autostart.enabled = enabled;
+                settings_manager.save(&settings).await
+                    .map_err(|err| anyhow::anyhow!("{}", err))?;
+            }
+
+            anyhow::Ok(())
+        }.await;
+
+        if let Err(err) = result {
+            error!("Could not check launch at login: {:?}", err);
+
+            if let Err(err) = app_handle.emit("autostart-failed", format!("{:#}", err)) {
+                error!("Could not emit autostart-failed: {}", err);
+            }
+        }
+    });
+}
+
+/// Forwards saved settings to the frontend, applies changed shortcuts and launch at login, restarts the process watcher when the sniffer settings changed.
+fn setup_settings_listener(
+    app_handle: AppHandle,
+    mut settings_rx: watch::Receiver<Settings>,
+    settings: Settings,
+    background_worker: Arc<Mutex<BackgroundWorker>>) {
+
+    let mut applied = settings;
+
+    tauri::async_runtime::spawn(async move {
+        while settings_rx.changed().await.is_ok() {
+            let settings = settings_rx.borrow_and_update().clone();
+
+            if let Err(err) = app_handle.emit("settings-changed", &settings) {
+                error!("Could not emit settings-changed: {}", err);
+            }
+
+            if settings.shortcuts != applied.shortcuts {
+                info!("shortcuts changed, registering them again");
+                register_shortcuts(&app_handle, &settings.shortcuts);
+            }
+
+            if settings.autostart.enabled != applied.autostart.enabled {
+                if let Err(err) = apply_autostart(&app_handle, settings.autostart.enabled) {
+                    error!("Could not update launch at login: {:?}", err);
+
+                    if let Err(err) = app_handle.emit("autostart-failed", format!("{:#}", err)) {
+                        error!("Could not emit autostart-failed: {}", err);
+                    }
+                }
+            }
+
+            if settings.logging != applied.logging {
+                set_log_levels(&settings.logging);
+            }
+
+            if settings.logging.retention_days != applied.logging.retention_days {
+                prune_old_logs(&app_handle.state::<Arc<AppPaths>>(), settings.logging.retention_days);
+            }
+
+            if settings.display != applied.display {
+                let registry = GetClassRegistryResult {
+                    classes: class_registry::resolve(&settings.display),
+                    display: settings.display.clone()
+                };
+
+                if let Err(err) = app_handle.emit("class-registry-changed", &registry) {
+                    error!("Could not emit class-registry-changed: {}", err);
+                }
+            }
+
+            if settings.windows != applied.windows {
+                apply_window_settings(&app_handle, &settings.windows);
+            }
+
+            applied = settings.clone();
+
+            let mut background_worker = background_worker.lock().await;
+
+            if *background_worker.sniffer_settings() == settings.sniffer {
+                continue;
+            }
+
+            info!("sniffer settings changed, restarting process watcher");
+
+            // the worker runs with the new settings either way, the error belongs to the previous run
+            if let Err(err) = background_worker.restart(settings.sniffer.clone()).await {
+                error!("Previous background worker failed: {:?}", err);
+
+                let watcher_error = WatcherError {
+                    message: format!("{:#}", err),
+                    occurred_on: Utc::now()
+                };
+
+                if let Err(err) = app_handle.emit("process-watcher-failed", watcher_error) {
+                    error!("Could not emit process-watcher-failed: {}", err);
+                }
+            }
+
+            if let Err(err) = app_handle.emit("sniffer-settings-applied", &settings.sniffer) {
+                error!("Could not emit sniffer-settings-applied: {}", err);
+            }
+        }
+    });
+}
+
+fn prune_old_logs(app_paths: &AppPaths, retention_days: u32) {
+    match prune_logs(&app_paths.log_dir, retention_days) {
+        Ok(0) => {},
+        Ok(removed) => info!("Removed {} log files older than {} days", removed, retention_days),
+        Err(err) => error!("Could not remove old log files: {:?}", err),
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex};
+
+use log::*;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::{instance_registry::InstanceRegistry, models::{ShortcutAction, ShortcutSettings, ShortcutStatus}};
+
+/// Global shortcuts currently registered with the OS and the click-through state they toggle.
+pub struct ShortcutRegistry {
+    statuses: Mutex<Vec<ShortcutStatus>>,
+    click_through: AtomicBool
+}
+
+impl ShortcutRegistry {
+    pub fn new() -> Self {
+        Self {
+            statuses: Mutex::new(vec![]),
+            click_through: AtomicBool::new(false)
+        }
+    }
+
+    pub fn statuses(&self) -> Vec<ShortcutStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+}
+
+/// Replaces every registered shortcut with the bindings of `shortcuts` and emits `shortcuts-registered`.
+///
+/// A combination another application already holds is reported in its status instead of failing the others.
+pub fn register_shortcuts(app_handle: &AppHandle, shortcuts: &ShortcutSettings) -> Vec<ShortcutStatus> {
+    let global_shortcut = app_handle.global_shortcut();
+
+    if let Err(err) = global_shortcut.unregister_all() {
+        error!("Could not unregister shortcuts: {}", err);
+    }
+
+    let statuses: Vec<_> = shortcuts.bindings()
+        .into_iter()
+        .map(|(action, shortcut)| {
+            let result = global_shortcut.on_shortcut(shortcut, move |app_handle, _, event| {
+                if event.state() == ShortcutState::Pressed {
+                    run_action(app_handle, action);
+                }
+            });
+
+            if let Err(err) = &result {
+                warn!("Could not register {} for {}: {}", shortcut, action.name(), err);
+            }
+
+            ShortcutStatus {
+                action,
+                shortcut: shortcut.to_string(),
+                registered: result.is_ok(),
+                error: result.err().map(|err| err.to_string())
+            }
+        })
+        .collect();
+
+    let registry = app_handle.state::<Arc<ShortcutRegistry>>();
+    *registry.statuses.lock().unwrap() = statuses.clone();
+
+    if let Err(err) = app_handle.emit("shortcuts-registered", &statuses) {
+        error!("Could not emit shortcuts-registered: {}", err);
+    }
+
+    statuses
+}
+
+/// The overlay and click-through are handled here, copying the summary is left to the
+/// frontend through `shortcut-triggered`.
+fn run_action(app_handle: &AppHandle, action: ShortcutAction) {
+    debug!("shortcut {}", action.name());
+
+    let result = match action {
+        ShortcutAction::ToggleOverlay => toggle_overlay(app_handle),
+        ShortcutAction::ToggleClickThrough => toggle_click_through(app_handle),
+        ShortcutAction::ResetEncounter => {
+            reset_encounter(app_handle.clone());
+            Ok(())
+        },
+        ShortcutAction::CopySummary => Ok(()),
+    };
+
+    if let Err(err) = result {
+        error!("Could not run {}: {}", action.name(), err);
+    }
+
+    if let Err(err) = app_handle.emit("shortcut-triggered", action) {
+        error!("Could not emit shortcut-triggered: {}", err);
+    }
+}
+
+fn toggle_overlay(app_handle: &AppHandle) -> tauri::Result<()> {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return Ok(());
+    };
+
+    if window.is_visible()? {
+        window.hide()
+    } else {
+        window.show()?;
+        window.set_focus()
+    }
+}
+
+fn toggle_click_through(app_handle: &AppHandle) -> tauri::Result<()> {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return Ok(());
+    };
+
+    let registry = app_handle.state::<Arc<ShortcutRegistry>>();
+    let click_through = !registry.click_through.load(Ordering::Relaxed);
+
+    window.set_ignore_cursor_events(click_through)?;
+    registry.click_through.store(click_through, Ordering::Relaxed);
+    app_handle.emit("click-through-changed", click_through)
+}
+
+/// Asks the processor of the instance shown in the UI to start a new encounter.
+fn reset_encounter(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let instance_registry = app_handle.state::<Arc<tokio::sync::Mutex<InstanceRegistry>>>();
+        let active_instance_id = instance_registry.lock().await.active_instance_id();
+
+        let Some(instance_id) = active_instance_id else {
+            debug!("no active instance to reset");
+            return;
+        };
+
+        if let Err(err) = app_handle.emit("reset-encounter", instance_id) {
+            error!("Could not emit reset-encounter: {}", err);
+        }
+    });
+}
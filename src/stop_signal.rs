@@ -0,0 +1,35 @@
+use std::{sync::{Condvar, Mutex}, time::Duration};
+
+/// Cancellation flag for worker threads, waking them up from [`StopSignal::wait_timeout`].
+pub struct StopSignal {
+    stopped: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl StopSignal {
+    pub fn new() -> Self {
+        Self {
+            stopped: Mutex::new(false),
+            condvar: Condvar::new(),
+        }
+    }
+
+    pub fn stop(&self) {
+        let mut stopped = self.stopped.lock().unwrap();
+        *stopped = true;
+        self.condvar.notify_all();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap()
+    }
+
+    /// Sleeps for `timeout` unless stopped in the meantime, returns whether the signal was stopped.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        let (stopped, _) = self.condvar
+            .wait_timeout_while(stopped, timeout, |stopped| !*stopped)
+            .unwrap();
+        *stopped
+    }
+}
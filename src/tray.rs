@@ -0,0 +1,50 @@
+use log::*;
+use tauri::{
+    menu::{Menu, MenuItem},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    App, AppHandle, Manager,
+};
+
+/// Tray icon to bring back the main window, e.g. after starting minimized at login.
+pub fn setup_tray(app: &App) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show, &quit])?;
+
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip("Lost Metrics")
+        .menu(&menu)
+        .menu_on_left_click(false)
+        .on_menu_event(|app_handle, event| match event.id.as_ref() {
+            "show" => show_main_window(app_handle),
+            "quit" => app_handle.exit(0),
+            _ => {},
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                show_main_window(tray.app_handle());
+            }
+        });
+
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+
+    builder.build(app)?;
+
+    Ok(())
+}
+
+pub fn show_main_window(app_handle: &AppHandle) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+
+    let result = window.unminimize()
+        .and_then(|_| window.show())
+        .and_then(|_| window.set_focus());
+
+    if let Err(err) = result {
+        error!("Could not show the main window: {}", err);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use log::*;
+use tauri::{AppHandle, LogicalSize, Manager, PhysicalPosition, PhysicalSize};
+use tauri_plugin_window_state::{AppHandleExt, StateFlags};
+
+use crate::models::WindowSettings;
+
+/// Geometry the window-state plugin saves and restores, visibility is left to the app
+/// so starting minimized keeps working.
+pub const WINDOW_STATE_FLAGS: StateFlags = StateFlags::all().difference(StateFlags::VISIBLE);
+
+/// Applies the options the window-state plugin doesn't cover to every open window that has settings.
+pub fn apply_window_settings(app_handle: &AppHandle, windows: &BTreeMap<String, WindowSettings>) {
+    for (label, settings) in windows {
+        let Some(window) = app_handle.get_webview_window(label) else {
+            continue;
+        };
+
+        if let Err(err) = window.set_always_on_top(settings.always_on_top) {
+            error!("Could not set always on top of {}: {}", label, err);
+        }
+    }
+}
+
+/// Moves every window back to its configured size in the middle of the primary monitor,
+/// e.g. after the monitor it was on has been disconnected, and saves that as the new state.
+pub fn reset_window_layout(app_handle: &AppHandle) -> Result<()> {
+    // `center()` would use the monitor the window is on, which may be the one that is gone
+    let primary_monitor = app_handle.primary_monitor()?;
+
+    for (label, window) in app_handle.webview_windows() {
+        let Some(config) = app_handle.config().app.windows.iter().find(|config| config.label == label) else {
+            continue;
+        };
+
+        info!("Resetting layout of {}", label);
+
+        let size = LogicalSize::new(config.width, config.height);
+        window.unmaximize()?;
+        window.set_size(size)?;
+
+        match &primary_monitor {
+            Some(monitor) => window.set_position(centered_position(
+                *monitor.position(),
+                *monitor.size(),
+                size.to_physical(monitor.scale_factor())))?,
+            None => {
+                warn!("No primary monitor found, centering {} on its current monitor", label);
+                window.center()?;
+            },
+        }
+    }
+
+    app_handle.save_window_state(WINDOW_STATE_FLAGS)?;
+
+    Ok(())
+}
+
+/// Top left corner of a window of `window_size` centered on a monitor, kept on the monitor
+/// when the window is larger than it.
+fn centered_position(
+    monitor_position: PhysicalPosition<i32>,
+    monitor_size: PhysicalSize<u32>,
+    window_size: PhysicalSize<u32>) -> PhysicalPosition<i32> {
+    let offset = |monitor: u32, window: u32| (monitor.saturating_sub(window) / 2) as i32;
+
+    PhysicalPosition::new(
+        monitor_position.x + offset(monitor_size.width, window_size.width),
+        monitor_position.y + offset(monitor_size.height, window_size.height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_center_window_on_monitor() {
+        let position = centered_position(
+            PhysicalPosition::new(-1920, 0),
+            PhysicalSize::new(1920, 1080),
+            PhysicalSize::new(800, 600));
+
+        assert_eq!(position, PhysicalPosition::new(-1360, 240));
+    }
+
+    #[test]
+    fn should_keep_oversized_window_on_monitor() {
+        let position = centered_position(
+            PhysicalPosition::new(0, 0),
+            PhysicalSize::new(1280, 720),
+            PhysicalSize::new(1600, 900));
+
+        assert_eq!(position, PhysicalPosition::new(0, 0));
+    }
+}